@@ -61,7 +61,7 @@ fn calculate_similarity_score(left_list: &[i32], right_list: &[i32]) -> i32 {
     .sum()
 }
 
-fn solve(input: &str, part: u8) -> i32 {
+pub(crate) fn solve(input: &str, part: u8) -> i32 {
   let (left_list, right_list) = parse_input(input).expect("Can't parse input.");
   match part {
     1 => calculate_total_distance(&left_list, &right_list),
@@ -70,7 +70,7 @@ fn solve(input: &str, part: u8) -> i32 {
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
   println!("Part 1 result = {}", solve(&input, 1));
@@ -78,7 +78,7 @@ fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day01_simple.txt", "Simple puzzle")?;
   print_result("input/day01_full.txt", "Full puzzle")?;
   Ok(())
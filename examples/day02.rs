@@ -54,13 +54,11 @@ impl Report {
       }
 
       // determine direction of first comparison
-      if is_increasing.is_none() {
-        is_increasing = Some(diff > 0);
-      } else {
-        let current_increasing = diff > 0;
-        if is_increasing.unwrap() != current_increasing {
-          return false;
-        }
+      let current_increasing = diff > 0;
+      match is_increasing {
+        None => is_increasing = Some(current_increasing),
+        Some(increasing) if increasing != current_increasing => return false,
+        Some(_) => {}
       }
     }
 
@@ -93,7 +91,7 @@ fn count_safe_reports_with_dampener(reports: &[Report]) -> usize {
     .count()
 }
 
-fn solve(input: &str, part: u8) -> usize {
+pub(crate) fn solve(input: &str, part: u8) -> usize {
   let reports = parse_input(input);
   match part {
     1 => count_safe_reports(&reports),
@@ -102,7 +100,7 @@ fn solve(input: &str, part: u8) -> usize {
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
   println!("Part 1 result = {}", solve(&input, 1));
@@ -110,7 +108,7 @@ fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day02_simple.txt", "Simple puzzle")?;
   print_result("input/day02_full.txt", "Full puzzle")?;
   Ok(())
@@ -47,7 +47,7 @@ fn calculate_sumproduct_with_instruction(input: &str) -> i32 {
   total
 }
 
-fn solve(input: &str, part: u8) -> i32 {
+pub(crate) fn solve(input: &str, part: u8) -> i32 {
   match part {
     1 => calculate_sumproduct(input),
     2 => calculate_sumproduct_with_instruction(input),
@@ -55,7 +55,7 @@ fn solve(input: &str, part: u8) -> i32 {
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
   println!("Part 1 result = {}", solve(&input, 1));
@@ -63,7 +63,7 @@ fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day03_full.txt", "Full puzzle")?;
   Ok(())
 }
@@ -96,7 +96,7 @@ fn count_x_mas(input: &str) -> usize {
   count
 }
 
-fn solve(input: &str, part: u8) -> usize {
+pub(crate) fn solve(input: &str, part: u8) -> usize {
   match part {
     1 => count_xmas(input),
     2 => count_x_mas(input),
@@ -104,7 +104,7 @@ fn solve(input: &str, part: u8) -> usize {
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
   println!("Part 1 result = {}", solve(&input, 1));
@@ -112,7 +112,7 @@ fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day04_simple.txt", "Simple puzzle")?;
   print_result("input/day04_full.txt", "Full puzzle")?;
   Ok(())
@@ -50,10 +50,10 @@ impl PrintQueue {
     for (i, &page_a) in update.iter().enumerate() {
       for &page_b in &update[i + 1..] {
         // check if page_b should come before page_a
-        if let Some(must_com_after) = self.ordering_rules.get(&page_b) {
-          if must_com_after.contains(&page_a) {
-            return false;
-          }
+        if let Some(must_com_after) = self.ordering_rules.get(&page_b)
+          && must_com_after.contains(&page_a)
+        {
+          return false;
         }
       }
     }
@@ -85,12 +85,12 @@ impl PrintQueue {
         let page_b = pages[i + 1];
 
         // check if page_b should come before page_a
-        if let Some(must_come_after) = self.ordering_rules.get(&page_b) {
-          if must_come_after.contains(&page_a) {
-            // swap them
-            pages.swap(i, i + 1);
-            changed = true;
-          }
+        if let Some(must_come_after) = self.ordering_rules.get(&page_b)
+          && must_come_after.contains(&page_a)
+        {
+          // swap them
+          pages.swap(i, i + 1);
+          changed = true;
         }
       }
     }
@@ -109,7 +109,7 @@ impl PrintQueue {
       .sum()
   }
 }
-fn solve(input: &str, part: u8) -> u32 {
+pub(crate) fn solve(input: &str, part: u8) -> u32 {
   let print_queue = PrintQueue::from_input(input);
   match part {
     1 => print_queue.sum_middle_pages_of_valid_updates(),
@@ -118,7 +118,7 @@ fn solve(input: &str, part: u8) -> u32 {
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
   println!("Part 1 result = {}", solve(&input, 1));
@@ -126,7 +126,7 @@ fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day05_simple.txt", "Simple puzzle")?;
   print_result("input/day05_full.txt", "Full puzzle")?;
   Ok(())
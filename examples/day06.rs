@@ -1,102 +1,12 @@
 use anyhow::Result;
+use aoc2024::grid::{Direction, Grid, Position};
 use std::collections::HashSet;
 use std::fs;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Direction {
-  Up,
-  Right,
-  Down,
-  Left,
-}
-
-impl Direction {
-  fn turn_right(self) -> Self {
-    match self {
-      Direction::Up => Direction::Right,
-      Direction::Right => Direction::Down,
-      Direction::Down => Direction::Left,
-      Direction::Left => Direction::Up,
-    }
-  }
-
-  fn delta(self) -> (i32, i32) {
-    match self {
-      Direction::Up => (-1, 0),
-      Direction::Right => (0, 1),
-      Direction::Down => (1, 0),
-      Direction::Left => (0, -1),
-    }
-  }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Position {
-  row: i32,
-  col: i32,
-}
-
-impl Position {
-  fn new(row: i32, col: i32) -> Self {
-    Self { row, col }
-  }
-
-  fn move_in_direction(self, direction: Direction) -> Self {
-    let (delta_row, delta_col) = direction.delta();
-    Self {
-      row: self.row + delta_row,
-      col: self.col + delta_col,
-    }
-  }
-}
-
-#[derive(Debug)]
-struct Grid {
-  cells: Vec<Vec<char>>,
-  rows: usize,
-  cols: usize,
-}
-
-impl Grid {
-  fn from_input(input: &str) -> Self {
-    let cells: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
-
-    let rows = cells.len();
-    let cols = if rows > 0 { cells[0].len() } else { 0 };
-
-    Self { cells, rows, cols }
-  }
-
-  fn is_valid_position(&self, pos: Position) -> bool {
-    pos.row >= 0 && pos.col >= 0 && (pos.row as usize) < self.rows && (pos.col as usize) < self.cols
-  }
-
-  fn get_cell(&self, pos: Position) -> Option<char> {
-    if self.is_valid_position(pos) {
-      Some(self.cells[pos.row as usize][pos.col as usize])
-    } else {
-      None
-    }
-  }
-
-  fn find_guard_start(&self) -> Option<(Position, Direction)> {
-    for (row_idx, row) in self.cells.iter().enumerate() {
-      for (col_idx, &cell) in row.iter().enumerate() {
-        let direction = match cell {
-          '^' => Some(Direction::Up),
-          '>' => Some(Direction::Right),
-          'v' => Some(Direction::Down),
-          '<' => Some(Direction::Left),
-          _ => None,
-        };
-
-        if let Some(dir) = direction {
-          return Some((Position::new(row_idx as i32, col_idx as i32), dir));
-        }
-      }
-    }
-    None
-  }
+/// Where the guard starts and which way its arrow points
+fn find_guard_start(grid: &Grid<char>) -> Option<(Position, Direction)> {
+  let pos = grid.find(|&cell| Direction::from_arrow(cell).is_some())?;
+  Some((pos, Direction::from_arrow(grid[pos])?))
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -107,17 +17,16 @@ struct GuardState {
 
 #[derive(Debug)]
 struct GuardSimulator {
-  grid: Grid,
+  grid: Grid<char>,
   guard_start_pos: Position,
   guard_start_dir: Direction,
 }
 
 impl GuardSimulator {
   fn new(input: &str) -> Result<Self, String> {
-    let grid = Grid::from_input(input);
-    let (guard_start_pos, guard_start_dir) = grid
-      .find_guard_start()
-      .ok_or("No guard found in the grid")?;
+    let grid = Grid::parse(input);
+    let (guard_start_pos, guard_start_dir) =
+      find_guard_start(&grid).ok_or("No guard found in the grid")?;
 
     Ok(Self {
       grid,
@@ -133,23 +42,21 @@ impl GuardSimulator {
     visited_positions.insert(guard_pos);
 
     loop {
-      let next_pos = guard_pos.move_in_direction(guard_dir);
+      let next_pos = guard_pos.step(guard_dir);
 
       // Check if guard would leave the grid
-      if !self.grid.is_valid_position(next_pos) {
+      let Some(&cell) = self.grid.get(next_pos) else {
         break;
-      }
+      };
 
       // Check if there's an obstacle in front
-      if let Some(cell) = self.grid.get_cell(next_pos) {
-        if cell == '#' {
-          // Turn right if obstacle found
-          guard_dir = guard_dir.turn_right();
-        } else {
-          // Move forward if no obstacle
-          guard_pos = next_pos;
-          visited_positions.insert(guard_pos);
-        }
+      if cell == '#' {
+        // Turn right if obstacle found
+        guard_dir = guard_dir.turn_clockwise();
+      } else {
+        // Move forward if no obstacle
+        guard_pos = next_pos;
+        visited_positions.insert(guard_pos);
       }
     }
 
@@ -174,23 +81,19 @@ impl GuardSimulator {
 
       visited_states.insert(current_state);
 
-      let next_pos = guard_pos.move_in_direction(guard_dir);
+      let next_pos = guard_pos.step(guard_dir);
 
       // Check if guard would leave the grid
-      if !self.grid.is_valid_position(next_pos) {
+      let Some(&cell) = self.grid.get(next_pos) else {
         return false;
-      }
+      };
 
       // Check if there's an obstacle in front (including our new obstruction)
-      let is_obstacle = if next_pos == obstruction_pos {
-        true
-      } else {
-        self.grid.get_cell(next_pos) == Some('#')
-      };
+      let is_obstacle = next_pos == obstruction_pos || cell == '#';
 
       if is_obstacle {
         // Turn right if obstacle found
-        guard_dir = guard_dir.turn_right();
+        guard_dir = guard_dir.turn_clockwise();
       } else {
         // Move forward if no obstacle
         guard_pos = next_pos;
@@ -219,7 +122,7 @@ impl GuardSimulator {
   }
 }
 
-fn solve(input: &str, part: u8) -> Result<usize> {
+pub(crate) fn solve(input: &str, part: u8) -> Result<usize> {
   let simulator = GuardSimulator::new(input).expect("Invalid input");
   match part {
     1 => Ok(simulator.simulate_patrol().len()),
@@ -228,7 +131,7 @@ fn solve(input: &str, part: u8) -> Result<usize> {
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   let result1 = solve(&input, 1)?;
   let result2 = solve(&input, 2)?;
@@ -238,7 +141,7 @@ fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day06_simple.txt", "Simple puzzle")?;
   print_result("input/day06_full.txt", "Full puzzle")?;
   Ok(())
@@ -99,7 +99,7 @@ fn get_total_calibration_result_with_concatenation(input: &str) -> u64 {
     .sum()
 }
 
-fn solve(input: &str, part: u8) -> u64 {
+pub(crate) fn solve(input: &str, part: u8) -> u64 {
   match part {
     1 => get_total_calibration_result(input),
     2 => get_total_calibration_result_with_concatenation(input),
@@ -107,7 +107,7 @@ fn solve(input: &str, part: u8) -> u64 {
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
   println!("Part 1 result = {}", solve(&input, 1));
@@ -115,7 +115,7 @@ fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day07_simple.txt", "Simple puzzle")?;
   print_result("input/day07_full.txt", "Full puzzle")?;
   Ok(())
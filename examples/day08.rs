@@ -3,29 +3,39 @@ use std::collections::{HashMap, HashSet};
 use std::fs;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Position {
-  row: i32,
-  col: i32,
+pub struct Position {
+  pub row: i32,
+  pub col: i32,
 }
 
 impl Position {
-  fn new(row: i32, col: i32) -> Self {
+  pub fn new(row: i32, col: i32) -> Self {
     Self { row, col }
   }
 
-  fn is_within_bounds(&self, grid_height: i32, grid_width: i32) -> bool {
+  pub fn is_within_bounds(&self, grid_height: i32, grid_width: i32) -> bool {
     self.row >= 0 && self.row < grid_height && self.col >= 0 && self.col < grid_width
   }
 }
 
-struct Grid {
+/// Antenna map: grid dimensions plus antenna positions grouped by frequency.
+///
+/// ```
+/// use aoc2024::days::day08::{Grid, Position};
+///
+/// let grid = Grid::parse("..a\n.a.\nb..");
+/// assert_eq!((grid.height(), grid.width()), (3, 3));
+/// assert_eq!(grid.positions_of('a'), [Position::new(0, 2), Position::new(1, 1)]);
+/// assert_eq!(grid.find_antinodes().len(), 1);
+/// ```
+pub struct Grid {
   height: i32,
   width: i32,
   antennas: HashMap<char, Vec<Position>>,
 }
 
 impl Grid {
-  fn parse(input: &str) -> Self {
+  pub fn parse(input: &str) -> Self {
     let lines: Vec<&str> = input.trim().lines().collect();
     let height = lines.len() as i32;
     let width = lines.first().map_or(0, |line| line.len()) as i32;
@@ -50,33 +60,80 @@ impl Grid {
     }
   }
 
-  fn find_antinodes(&self) -> HashSet<Position> {
+  pub fn height(&self) -> i32 {
+    self.height
+  }
+
+  pub fn width(&self) -> i32 {
+    self.width
+  }
+
+  /// Every distinct antenna frequency present on the grid (in no particular order).
+  pub fn antenna_frequencies(&self) -> impl Iterator<Item = char> + '_ {
+    self.antennas.keys().copied()
+  }
+
+  /// Positions of all antennas tuned to `freq`, in reading order; empty if none.
+  pub fn positions_of(&self, freq: char) -> &[Position] {
+    self.antennas.get(&freq).map_or(&[], Vec::as_slice)
+  }
+
+  pub fn find_antinodes(&self) -> HashSet<Position> {
     let mut antinodes = HashSet::new();
 
     for positions in self.antennas.values() {
       for (i, &pos1) in positions.iter().enumerate() {
         for &pos2 in positions.iter().skip(i + 1) {
-          // Antinode 1: pos1 - (pos2 - pos1) = 2*pos1 - pos2
-          let antinode1 = Position::new(2 * pos1.row - pos2.row, 2 * pos1.col - pos2.col);
+          for antinode in pair_antinodes(pos1, pos2) {
+            if antinode.is_within_bounds(self.height, self.width) {
+              antinodes.insert(antinode);
+            }
+          }
+        }
+      }
+    }
 
-          // Antinode 2: pos2 + (pos2 - pos1) = 2*pos2 - pos1
-          let antinode2 = Position::new(2 * pos2.row - pos1.row, 2 * pos2.col - pos1.col);
+    antinodes
+  }
 
-          if antinode1.is_within_bounds(self.height, self.width) {
-            antinodes.insert(antinode1);
-          }
+  /// All reflected antinodes on an infinite plane, deduplicated and ignoring grid bounds.
+  pub fn find_antinodes_unbounded(&self) -> Vec<Position> {
+    let mut antinodes = HashSet::new();
 
-          if antinode2.is_within_bounds(self.height, self.width) {
-            antinodes.insert(antinode2);
-          }
+    for positions in self.antennas.values() {
+      for (i, &pos1) in positions.iter().enumerate() {
+        for &pos2 in positions.iter().skip(i + 1) {
+          antinodes.extend(pair_antinodes(pos1, pos2));
         }
       }
     }
 
-    antinodes
+    antinodes.into_iter().collect()
   }
 
-  fn find_antinodes_alternatively(&self) -> HashSet<Position> {
+  /// Count unique in-bounds antinodes contributed by each frequency,
+  /// before deduplicating across frequencies.
+  pub fn antinodes_by_frequency(&self) -> HashMap<char, usize> {
+    self
+      .antenna_frequencies()
+      .map(|freq| {
+        let positions = self.positions_of(freq);
+        let mut antinodes = HashSet::new();
+        for (i, &pos1) in positions.iter().enumerate() {
+          for &pos2 in positions.iter().skip(i + 1) {
+            for antinode in pair_antinodes(pos1, pos2) {
+              if antinode.is_within_bounds(self.height(), self.width()) {
+                antinodes.insert(antinode);
+              }
+            }
+          }
+        }
+        (freq, antinodes.len())
+      })
+      .collect()
+  }
+
+  pub fn find_antinodes_alternatively(&self) -> HashSet<Position> {
     let mut antinodes = HashSet::new();
 
     for positions in self.antennas.values() {
@@ -127,11 +184,20 @@ impl Grid {
   }
 }
 
+/// The two antinodes of an antenna pair, each as far beyond one antenna as the other
+/// antenna is on the near side: `2*pos1 - pos2` and `2*pos2 - pos1`
+fn pair_antinodes(pos1: Position, pos2: Position) -> [Position; 2] {
+  [
+    Position::new(2 * pos1.row - pos2.row, 2 * pos1.col - pos2.col),
+    Position::new(2 * pos2.row - pos1.row, 2 * pos2.col - pos1.col),
+  ]
+}
+
 fn gcd(a: i32, b: i32) -> i32 {
   if b == 0 { a } else { gcd(b, a % b) }
 }
 
-fn solve(input: &str, part: u8) -> usize {
+pub(crate) fn solve(input: &str, part: u8) -> usize {
   let grid = Grid::parse(input);
   match part {
     1 => grid.find_antinodes().len(),
@@ -140,16 +206,79 @@ fn solve(input: &str, part: u8) -> usize {
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
   println!("Part 1 result = {}", solve(&input, 1));
-  println!("Part 2 result = {}\n", solve(&input, 2));
+  println!("Part 2 result = {}", solve(&input, 2));
+
+  let grid = Grid::parse(&input);
+  println!(
+    "Unbounded antinodes = {}",
+    grid.find_antinodes_unbounded().len()
+  );
+
+  let mut by_freq: Vec<_> = grid.antinodes_by_frequency().into_iter().collect();
+  by_freq.sort_unstable();
+  let tallies: Vec<String> = by_freq
+    .iter()
+    .map(|(freq, count)| format!("{freq}={count}"))
+    .collect();
+  println!("Antinodes by frequency: {}\n", tallies.join(", "));
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day08_simple.txt", "Simple puzzle")?;
   print_result("input/day08_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day08_simple.txt");
+
+  #[test]
+  fn sample_answers() {
+    assert_eq!(solve(SAMPLE, 1), 14);
+    assert_eq!(solve(SAMPLE, 2), 34);
+  }
+
+  #[test]
+  fn grid_accessors() {
+    let grid = Grid::parse("..a\n.a.\nb..");
+    assert_eq!((grid.height(), grid.width()), (3, 3));
+    assert_eq!(
+      grid.positions_of('a'),
+      &[Position::new(0, 2), Position::new(1, 1)]
+    );
+    assert!(grid.positions_of('c').is_empty());
+
+    let mut frequencies: Vec<char> = grid.antenna_frequencies().collect();
+    frequencies.sort_unstable();
+    assert_eq!(frequencies, ['a', 'b']);
+  }
+
+  #[test]
+  fn antinodes_by_frequency() {
+    let grid = Grid::parse("..a\n.a.\nb..");
+    let by_freq = grid.antinodes_by_frequency();
+    assert_eq!(by_freq[&'a'], 1);
+    assert_eq!(by_freq[&'b'], 0);
+
+    // One antinode is shared by both frequencies, hence 14 overall
+    let by_freq = Grid::parse(SAMPLE).antinodes_by_frequency();
+    assert_eq!((by_freq[&'0'], by_freq[&'A']), (10, 5));
+  }
+
+  #[test]
+  fn unbounded_antinodes_include_those_off_the_grid() {
+    let grid = Grid::parse("..a\n.a.\n...");
+    let mut antinodes = grid.find_antinodes_unbounded();
+    antinodes.sort_by_key(|pos| (pos.row, pos.col));
+    assert_eq!(antinodes, [Position::new(-1, 3), Position::new(2, 0)]);
+    assert_eq!(grid.find_antinodes().len(), 1);
+  }
+}
@@ -1,4 +1,6 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
 use std::fs;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -21,35 +23,56 @@ impl Block {
 }
 
 #[derive(Debug)]
-struct Disk {
+pub struct Disk {
   blocks: Vec<Block>,
 }
 
-impl Disk {
-  fn from_disk_map(disk_map: &str) -> Self {
-    let mut blocks = Vec::new();
-    let mut file_id = 0u32;
-    let mut is_file = true;
-
-    for digit_char in disk_map.trim().chars() {
-      let length = digit_char.to_digit(10).expect("Invalid digit in disk map") as usize;
-
-      if is_file {
-        // Add file blocks
-        blocks.extend(std::iter::repeat_n(Block::File(file_id), length));
-        file_id += 1;
-      } else {
-        // Add free space blocks
-        blocks.extend(std::iter::repeat_n(Block::Free, length));
-      }
+/// Parse a disk map into alternating file/free runs (zero-length runs included).
+/// A trailing file length with no following free span is valid and simply ends the disk.
+fn parse_disk_map(disk_map: &str) -> Result<Vec<Segment>> {
+  // Positions count from the start of the untrimmed input
+  let leading_whitespace = disk_map.chars().take_while(|c| c.is_whitespace()).count();
+  let mut lengths = Vec::new();
+  for (position, digit_char) in disk_map.trim().chars().enumerate() {
+    let Some(length) = digit_char.to_digit(10) else {
+      let position = leading_whitespace + position;
+      bail!("invalid character {digit_char:?} at position {position} in disk map");
+    };
+    lengths.push(length as usize);
+  }
 
-      is_file = !is_file;
+  let mut segments = Vec::with_capacity(lengths.len());
+  for (file_id, pair) in lengths.chunks(2).enumerate() {
+    segments.push(Segment {
+      file_id: Some(file_id as u32),
+      len: pair[0],
+    });
+    // An odd number of digits leaves the last file without a free span
+    if let Some(&free_len) = pair.get(1) {
+      segments.push(Segment {
+        file_id: None,
+        len: free_len,
+      });
     }
+  }
+
+  Ok(segments)
+}
 
-    Self { blocks }
+impl Disk {
+  pub fn from_disk_map(disk_map: &str) -> Result<Self> {
+    let blocks = parse_disk_map(disk_map)?
+      .into_iter()
+      .flat_map(|segment| {
+        let block = segment.file_id.map_or(Block::Free, Block::File);
+        std::iter::repeat_n(block, segment.len)
+      })
+      .collect();
+
+    Ok(Self { blocks })
   }
 
-  fn compact(&mut self) {
+  pub fn compact(&mut self) {
     let mut left = 0;
     let mut right = self.blocks.len().saturating_sub(1);
 
@@ -73,69 +96,12 @@ impl Disk {
     }
   }
 
-  fn compact_whole_files(&mut self) {
-    // Get the highest file ID
-    let max_file_id = self
-      .blocks
-      .iter()
-      .filter_map(|block| block.file_id())
-      .max()
-      .unwrap_or(0);
-
-    // Process files in decreasing order of file ID
-    for file_id in (0..=max_file_id).rev() {
-      if let Some((file_start, file_size)) = self.find_file(file_id) {
-        if let Some(free_start) = self.find_free_space_before(file_start, file_size) {
-          // Move the entire file
-          for i in 0..file_size {
-            self.blocks[free_start + i] = Block::File(file_id);
-            self.blocks[file_start + i] = Block::Free;
-          }
-        }
-      }
-    }
-  }
-
-  fn find_file(&self, file_id: u32) -> Option<(usize, usize)> {
-    let start = self
-      .blocks
-      .iter()
-      .position(|&block| block == Block::File(file_id))?;
-
-    let size = self.blocks[start..]
-      .iter()
-      .take_while(|&&block| block == Block::File(file_id))
-      .count();
-
-    Some((start, size))
-  }
-
-  fn find_free_space_before(&self, before_position: usize, required_size: usize) -> Option<usize> {
-    let mut current_free_start = None;
-    let mut current_free_size = 0;
-
-    for (i, &block) in self.blocks[..before_position].iter().enumerate() {
-      if block.is_free() {
-        if current_free_start.is_none() {
-          current_free_start = Some(i);
-          current_free_size = 1;
-        } else {
-          current_free_size += 1;
-        }
-
-        if current_free_size >= required_size {
-          return current_free_start;
-        }
-      } else {
-        current_free_start = None;
-        current_free_size = 0;
-      }
-    }
-
-    None
+  /// (number of free gaps, total free blocks) between the first and last file block
+  pub fn fragmentation(&self) -> (usize, usize) {
+    fragmentation(self.segments())
   }
 
-  fn checksum(&self) -> u64 {
+  pub fn checksum(&self) -> u64 {
     self
       .blocks
       .iter()
@@ -161,28 +127,406 @@ impl Disk {
       })
       .collect()
   }
+
+  /// Run-grouped rendering that stays readable for any file ID, e.g. `0·2 .·3 1·3`
+  pub fn display_verbose(&self) -> String {
+    self
+      .segments()
+      .map(|(file_id, _, len)| match file_id {
+        Some(id) => format!("{id}·{len}"),
+        None => format!(".·{len}"),
+      })
+      .collect::<Vec<_>>()
+      .join(" ")
+  }
+
+  /// Contiguous runs as (file_id, start, len); free space has `file_id == None`
+  pub fn segments(&self) -> impl Iterator<Item = (Option<u32>, usize, usize)> + '_ {
+    self.blocks.chunk_by(|a, b| a == b).scan(0, |start, run| {
+      let segment = (run[0].file_id(), *start, run.len());
+      *start += run.len();
+      Some(segment)
+    })
+  }
+}
+
+/// (number of free gaps, total free blocks) between the first and last file run.
+/// Neighbouring free runs count as one gap.
+fn fragmentation(runs: impl Iterator<Item = (Option<u32>, usize, usize)>) -> (usize, usize) {
+  let runs: Vec<_> = runs.filter(|&(_, _, len)| len > 0).collect();
+  let Some(first) = runs.iter().position(|(file_id, ..)| file_id.is_some()) else {
+    return (0, 0);
+  };
+  let last = runs
+    .iter()
+    .rposition(|(file_id, ..)| file_id.is_some())
+    .unwrap();
+
+  let mut gaps = 0;
+  let mut free_blocks = 0;
+  let mut in_gap = false;
+  for &(file_id, _, len) in &runs[first..=last] {
+    if file_id.is_none() {
+      free_blocks += len;
+      if !in_gap {
+        gaps += 1;
+      }
+    }
+    in_gap = file_id.is_none();
+  }
+
+  (gaps, free_blocks)
+}
+
+/// A contiguous run of blocks: either one file (`Some(id)`) or free space (`None`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+  file_id: Option<u32>,
+  len: usize,
+}
+
+/// Run-length disk layout; avoids expanding every block and keeps whole-file moves cheap
+#[derive(Debug)]
+pub struct SegmentDisk {
+  segments: Vec<Segment>,
+}
+
+impl SegmentDisk {
+  pub fn from_disk_map(disk_map: &str) -> Result<Self> {
+    let segments = parse_disk_map(disk_map)?
+      .into_iter()
+      .filter(|segment| segment.len > 0)
+      .collect();
+
+    Ok(Self { segments })
+  }
+
+  /// Move each file, highest ID first, into the leftmost free span before it that can
+  /// hold it whole. Free spans are kept by start offset, and each file length has a
+  /// cursor below which no span is long enough: spans only shrink as files move in, and
+  /// a vacated file pulls the cursors back to the span it frees.
+  pub fn compact_whole_files(&mut self) {
+    let mut files = Vec::new();
+    let mut free = BTreeMap::new();
+    for (file_id, start, len) in self.segments() {
+      match file_id {
+        Some(id) => files.push((id, start, len)),
+        None => {
+          free_span(&mut free, start, len);
+        }
+      }
+    }
+    files.sort_unstable_by_key(|&(id, _, _)| Reverse(id));
+
+    let max_len = files.iter().map(|&(_, _, len)| len).max().unwrap_or(0);
+    let mut cursors = vec![0; max_len + 1];
+    for (_, start, len) in &mut files {
+      let (file_start, file_len) = (*start, *len);
+      let cursor = cursors[file_len];
+      let found = (cursor < file_start)
+        .then(|| {
+          free
+            .range(cursor..file_start)
+            .find(|&(_, &span_len)| span_len >= file_len)
+        })
+        .flatten()
+        .map(|(&span_start, &span_len)| (span_start, span_len));
+      let Some((span_start, span_len)) = found else {
+        cursors[file_len] = cursor.max(file_start);
+        continue;
+      };
+
+      cursors[file_len] = span_start;
+      free.remove(&span_start);
+      if span_len > file_len {
+        free.insert(span_start + file_len, span_len - file_len);
+      }
+      *start = span_start;
+
+      let (merged_start, merged_len) = free_span(&mut free, file_start, file_len);
+      for cursor in &mut cursors[..=merged_len.min(max_len)] {
+        *cursor = (*cursor).min(merged_start);
+      }
+    }
+
+    let mut runs: Vec<(usize, Segment)> = files
+      .into_iter()
+      .map(|(id, start, len)| {
+        (
+          start,
+          Segment {
+            file_id: Some(id),
+            len,
+          },
+        )
+      })
+      .chain(
+        free
+          .into_iter()
+          .map(|(start, len)| (start, Segment { file_id: None, len })),
+      )
+      .collect();
+    runs.sort_unstable_by_key(|&(start, _)| start);
+    self.segments = runs.into_iter().map(|(_, segment)| segment).collect();
+  }
+
+  /// Merge neighbouring free segments into a single span
+  pub fn coalesce_free(&mut self) {
+    self.segments.dedup_by(|next, prev| {
+      if prev.file_id.is_none() && next.file_id.is_none() {
+        prev.len += next.len;
+        true
+      } else {
+        false
+      }
+    });
+  }
+
+  /// Same measure as `Disk::fragmentation`, so part 1 and part 2 layouts compare directly
+  pub fn fragmentation(&self) -> (usize, usize) {
+    fragmentation(self.segments())
+  }
+
+  /// Contiguous runs as (file_id, start, len), matching `Disk::segments`
+  ///
+  /// ```
+  /// use aoc2024::days::day09::SegmentDisk;
+  ///
+  /// let disk = SegmentDisk::from_disk_map("12101").unwrap();
+  /// let runs: Vec<_> = disk.segments().collect();
+  /// assert_eq!(runs, [(Some(0), 0, 1), (None, 1, 2), (Some(1), 3, 1), (Some(2), 4, 1)]);
+  /// ```
+  pub fn segments(&self) -> impl Iterator<Item = (Option<u32>, usize, usize)> + '_ {
+    self.segments.iter().scan(0, |start, segment| {
+      let run = (segment.file_id, *start, segment.len);
+      *start += segment.len;
+      Some(run)
+    })
+  }
+
+  pub fn checksum(&self) -> u64 {
+    let mut offset = 0u64;
+    let mut sum = 0u64;
+    for segment in &self.segments {
+      let len = segment.len as u64;
+      if let Some(id) = segment.file_id {
+        // id * (offset + (offset + 1) + ... + (offset + len - 1))
+        sum += id as u64 * (len * offset + len * (len - 1) / 2);
+      }
+      offset += len;
+    }
+    sum
+  }
 }
 
-fn solve(input: &str, part: u8) -> u64 {
-  let mut disk = Disk::from_disk_map(input);
-  match part {
-    1 => disk.compact(),
-    2 => disk.compact_whole_files(),
+/// Add a free span to the by-offset map, merging it with any free span it touches.
+/// Returns the merged (start, len).
+fn free_span(free: &mut BTreeMap<usize, usize>, start: usize, len: usize) -> (usize, usize) {
+  let (mut start, mut len) = (start, len);
+  if let Some((&prev_start, &prev_len)) = free.range(..start).next_back()
+    && prev_start + prev_len == start
+  {
+    free.remove(&prev_start);
+    (start, len) = (prev_start, prev_len + len);
+  }
+  if let Some(next_len) = free.remove(&(start + len)) {
+    len += next_len;
+  }
+  free.insert(start, len);
+  (start, len)
+}
+
+pub(crate) fn solve(input: &str, part: u8) -> Result<u64> {
+  let checksum = match part {
+    1 => {
+      let mut disk = Disk::from_disk_map(input)?;
+      disk.compact();
+      disk.checksum()
+    }
+    2 => {
+      let mut disk = SegmentDisk::from_disk_map(input)?;
+      disk.compact_whole_files();
+      disk.checksum()
+    }
     _ => panic!("Only parts 1 and 2."),
   };
-  disk.checksum()
+  Ok(checksum)
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
-  println!("Part 1 result = {}", solve(&input, 1));
-  println!("Part 2 result = {}\n", solve(&input, 2));
+  println!("Part 1 result = {}", solve(&input, 1)?);
+  println!("Part 2 result = {}\n", solve(&input, 2)?);
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day09_simple.txt", "Simple puzzle")?;
   print_result("input/day09_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day09_simple.txt");
+
+  /// Whole-file compaction done block by block, as a reference for `SegmentDisk`
+  fn compact_blocks(mut blocks: Vec<Option<u32>>) -> Vec<Option<u32>> {
+    let max_file_id = blocks.iter().flatten().copied().max().unwrap_or(0);
+    for file_id in (0..=max_file_id).rev() {
+      let Some(file_start) = blocks.iter().position(|&block| block == Some(file_id)) else {
+        continue;
+      };
+      let file_len = blocks[file_start..]
+        .iter()
+        .take_while(|&&block| block == Some(file_id))
+        .count();
+      let fits = (0..file_start).find(|&start| {
+        start + file_len <= file_start
+          && blocks[start..start + file_len].iter().all(Option::is_none)
+      });
+      if let Some(free_start) = fits {
+        blocks[free_start..free_start + file_len].fill(Some(file_id));
+        blocks[file_start..file_start + file_len].fill(None);
+      }
+    }
+    blocks
+  }
+
+  fn blocks(disk: &SegmentDisk) -> Vec<Option<u32>> {
+    disk
+      .segments()
+      .flat_map(|(file_id, _, len)| std::iter::repeat_n(file_id, len))
+      .collect()
+  }
+
+  #[test]
+  fn segment_compaction_matches_block_compaction() {
+    let mut maps = vec![
+      SAMPLE.trim().to_string(),
+      // Zero-length free spans between files, and zero-length files between free spans
+      "1020304".to_string(),
+      "2303030".to_string(),
+      // File 2 can't move: no span before it holds three blocks
+      "11123".to_string(),
+      "90909".to_string(),
+    ];
+    // A longer pseudo-random map
+    let mut seed = 12345u64;
+    maps.push(
+      (0..400)
+        .map(|_| {
+          seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+          char::from(b'0' + (seed >> 33) as u8 % 10)
+        })
+        .collect(),
+    );
+
+    for map in &maps {
+      let mut disk = SegmentDisk::from_disk_map(map).unwrap();
+      let expected = compact_blocks(blocks(&disk));
+      disk.compact_whole_files();
+      assert_eq!(blocks(&disk), expected, "{map}");
+      // A second pass starts from a layout whose files are out of ID order
+      let expected = compact_blocks(expected);
+      disk.compact_whole_files();
+      assert_eq!(blocks(&disk), expected, "{map}");
+    }
+
+    assert_eq!(solve(SAMPLE, 1).unwrap(), 1928);
+    assert_eq!(solve(SAMPLE, 2).unwrap(), 2858);
+  }
+
+  #[test]
+  fn segments_iterate_runs_with_offsets() {
+    let expected = [
+      (Some(0), 0, 1),
+      (None, 1, 2),
+      (Some(1), 3, 3),
+      (None, 6, 4),
+      (Some(2), 10, 5),
+    ];
+    let disk = Disk::from_disk_map("12345").unwrap();
+    assert_eq!(disk.segments().collect::<Vec<_>>(), expected);
+    let disk = SegmentDisk::from_disk_map("12345").unwrap();
+    assert_eq!(disk.segments().collect::<Vec<_>>(), expected);
+  }
+
+  #[test]
+  fn verbose_display_handles_multi_digit_ids() {
+    let disk = Disk::from_disk_map(&"12".repeat(11)).unwrap();
+    assert!(disk.display().ends_with("9..?.."));
+    assert!(disk.display_verbose().ends_with("9·1 .·2 10·1 .·2"));
+  }
+
+  #[test]
+  fn fragmentation_of_part1_and_part2_layouts() {
+    let mut disk = Disk::from_disk_map(SAMPLE).unwrap();
+    assert_eq!(disk.fragmentation(), (8, 14));
+    disk.compact();
+    assert_eq!(disk.fragmentation(), (0, 0));
+
+    // 00992111777.44.333....5555.6666.....8888..
+    let mut segment_disk = SegmentDisk::from_disk_map(SAMPLE).unwrap();
+    assert_eq!(segment_disk.fragmentation(), (8, 14));
+    segment_disk.compact_whole_files();
+    assert_eq!(segment_disk.fragmentation(), (5, 12));
+  }
+
+  #[test]
+  fn coalescing_lets_a_later_pass_move_a_file() {
+    // 0.1..222: file 2 fits in neither free span until file 1 moves out from between them
+    let mut disk = SegmentDisk::from_disk_map("11123").unwrap();
+    disk.compact_whole_files();
+    let runs: Vec<_> = disk.segments().collect();
+    assert_eq!(
+      runs,
+      [
+        (Some(0), 0, 1),
+        (Some(1), 1, 1),
+        (None, 2, 3),
+        (Some(2), 5, 3)
+      ]
+    );
+
+    disk.compact_whole_files();
+    let runs: Vec<_> = disk.segments().collect();
+    assert_eq!(
+      runs,
+      [
+        (Some(0), 0, 1),
+        (Some(1), 1, 1),
+        (Some(2), 2, 3),
+        (None, 5, 3)
+      ]
+    );
+  }
+
+  #[test]
+  fn rejects_a_letter_at_its_input_position() {
+    let error = Disk::from_disk_map("12x45").unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "invalid character 'x' at position 2 in disk map"
+    );
+    let error = SegmentDisk::from_disk_map("\n  12x45\n").unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "invalid character 'x' at position 5 in disk map"
+    );
+  }
+
+  #[test]
+  fn odd_digit_count_ends_with_a_file() {
+    // 0..111.22
+    let disk = SegmentDisk::from_disk_map("12312").unwrap();
+    let runs: Vec<_> = disk.segments().collect();
+    assert_eq!(runs.last(), Some(&(Some(2), 7, 2)));
+    assert_eq!(Disk::from_disk_map("12312").unwrap().blocks.len(), 9);
+  }
+}
@@ -1,39 +1,59 @@
-use anyhow::Result;
-use std::collections::{HashSet, VecDeque};
+use anyhow::{Result, bail};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::{fs, panic};
 
-type Position = (usize, usize);
+pub type Position = (usize, usize);
+
+/// Height assigned to '.' cells; no trail step can enter or leave it
+const IMPASSABLE: u8 = u8::MAX;
+
+/// Height gained per trail step unless a caller asks for another slope
+const DEFAULT_STEP: u8 = 1;
 
 #[derive(Debug)]
-struct TopographicMap {
+pub struct TopographicMap {
   grid: Vec<Vec<u8>>,
   rows: usize,
   cols: usize,
 }
 
 impl TopographicMap {
-  fn new(input: &str) -> Self {
-    let grid: Vec<Vec<u8>> = input
-      .lines()
-      .map(|line| {
-        line
-          .chars()
-          .map(|c| c.to_digit(10).unwrap() as u8)
-          .collect()
-      })
-      .collect();
+  pub fn new(input: &str) -> Result<Self> {
+    let mut grid: Vec<Vec<u8>> = Vec::new();
+    // Blank lines (such as a trailing one) are not rows
+    let lines = input.lines().filter(|line| !line.trim().is_empty());
+    for (row_idx, line) in lines.enumerate() {
+      let mut row = Vec::with_capacity(line.len());
+      for (col_idx, c) in line.chars().enumerate() {
+        let height = match c {
+          '.' => IMPASSABLE,
+          _ => match c.to_digit(10) {
+            Some(digit) => digit as u8,
+            None => bail!("invalid character {c:?} at row {row_idx}, column {col_idx}"),
+          },
+        };
+        row.push(height);
+      }
+      grid.push(row);
+    }
 
     let rows = grid.len();
     let cols = grid.first().map_or(0, |row| row.len());
+    if let Some(row_idx) = grid.iter().position(|row| row.len() != cols) {
+      bail!(
+        "row {row_idx} has {} columns, expected {cols}",
+        grid[row_idx].len()
+      );
+    }
 
-    Self { grid, rows, cols }
+    Ok(Self { grid, rows, cols })
   }
 
   fn height_at(&self, pos: Position) -> u8 {
     self.grid[pos.0][pos.1]
   }
 
-  fn find_trailheads(&self) -> Vec<Position> {
+  pub fn find_trailheads(&self) -> Vec<Position> {
     let mut trailheads = Vec::new();
 
     for (row_idx, row) in self.grid.iter().enumerate() {
@@ -48,6 +68,10 @@ impl TopographicMap {
   }
 
   fn get_valid_neighbors(&self, pos: Position) -> Vec<Position> {
+    self.get_valid_neighbors_with_step(pos, DEFAULT_STEP)
+  }
+
+  fn get_valid_neighbors_with_step(&self, pos: Position, step: u8) -> Vec<Position> {
     let (row, col) = pos;
     let current_height = self.height_at(pos);
     let mut neighbors = Vec::new();
@@ -69,8 +93,8 @@ impl TopographicMap {
         let new_pos = (new_row as usize, new_col as usize);
         let new_height = self.height_at(new_pos);
 
-        // Valid trail step: height increases by exactly 1
-        if new_height == current_height + 1 {
+        // Valid trail step: height increases by exactly `step`
+        if new_height != IMPASSABLE && current_height.checked_add(step) == Some(new_height) {
           neighbors.push(new_pos);
         }
       }
@@ -79,7 +103,7 @@ impl TopographicMap {
     neighbors
   }
 
-  fn calculate_trailhead_score(&self, trailhead: Position) -> usize {
+  fn calculate_trailhead_score(&self, trailhead: Position, step: u8) -> usize {
     let mut reachable_nines = HashSet::new();
     let mut queue = VecDeque::new();
     let mut visited = HashSet::new();
@@ -97,7 +121,7 @@ impl TopographicMap {
       }
 
       // Explore valid neighbors
-      for neighbor_pos in self.get_valid_neighbors(current_pos) {
+      for neighbor_pos in self.get_valid_neighbors_with_step(current_pos, step) {
         if visited.insert(neighbor_pos) {
           queue.push_back(neighbor_pos);
         }
@@ -107,62 +131,272 @@ impl TopographicMap {
     reachable_nines.len()
   }
 
-  fn calculate_trailhead_rating(&self, trailhead: Position) -> usize {
-    self.count_distinct_trails(trailhead)
+  fn calculate_trailhead_rating(
+    &self,
+    trailhead: Position,
+    step: u8,
+    memo: &mut HashMap<Position, usize>,
+  ) -> usize {
+    self.count_distinct_trails(trailhead, step, memo)
   }
 
-  fn count_distinct_trails(&self, pos: Position) -> usize {
+  /// Trails-to-9 from a cell depend only on its position (heights are fixed),
+  /// so `memo` can be shared across trailheads as long as `step` stays the same.
+  fn count_distinct_trails(
+    &self,
+    pos: Position,
+    step: u8,
+    memo: &mut HashMap<Position, usize>,
+  ) -> usize {
+    if let Some(&count) = memo.get(&pos) {
+      return count;
+    }
+
     let current_height = self.height_at(pos);
 
     // Base case: if we reached height 9, this is one complete trail
-    if current_height == 9 {
-      return 1;
+    let count = if current_height == 9 {
+      1
+    } else {
+      // Count all possible trails from valid neighbors
+      self
+        .get_valid_neighbors_with_step(pos, step)
+        .iter()
+        .map(|&neighbor_pos| self.count_distinct_trails(neighbor_pos, step, memo))
+        .sum()
+    };
+
+    memo.insert(pos, count);
+    count
+  }
+
+  /// Number of distinct trails to a 9 from every cell, computed bottom-up without recursion.
+  /// Cells are processed from highest to lowest so each neighbor is final before it is read.
+  fn trail_counts(&self) -> Vec<Vec<usize>> {
+    let mut cells: Vec<Position> = (0..self.rows)
+      .flat_map(|row| (0..self.cols).map(move |col| (row, col)))
+      .collect();
+    cells.sort_unstable_by_key(|&pos| std::cmp::Reverse(self.height_at(pos)));
+
+    let mut counts = vec![vec![0; self.cols]; self.rows];
+    for pos in cells {
+      counts[pos.0][pos.1] = if self.height_at(pos) == 9 {
+        1
+      } else {
+        self
+          .get_valid_neighbors(pos)
+          .iter()
+          .map(|&(row, col)| counts[row][col])
+          .sum()
+      };
     }
 
-    // Count all possible trails from valid neighbors
+    counts
+  }
+
+  pub fn sum_ratings_iterative(&self) -> usize {
+    let counts = self.trail_counts();
     self
-      .get_valid_neighbors(pos)
+      .find_trailheads()
       .iter()
-      .map(|&neighbor_pos| self.count_distinct_trails(neighbor_pos))
+      .map(|&(row, col)| counts[row][col])
       .sum()
   }
 
-  fn sum_scores(&self) -> usize {
+  /// Every distinct increasing 0→9 path from `trailhead`; the rating is the number of paths.
+  pub fn enumerate_trails(&self, trailhead: Position) -> Vec<Vec<Position>> {
+    let mut trails = Vec::new();
+    let mut path = vec![trailhead];
+    self.extend_trails(&mut path, &mut trails);
+    trails
+  }
+
+  fn extend_trails(&self, path: &mut Vec<Position>, trails: &mut Vec<Vec<Position>>) {
+    let pos = *path.last().unwrap();
+    if self.height_at(pos) == 9 {
+      trails.push(path.clone());
+      return;
+    }
+
+    for neighbor_pos in self.get_valid_neighbors(pos) {
+      path.push(neighbor_pos);
+      self.extend_trails(path, trails);
+      path.pop();
+    }
+  }
+
+  /// Score (distinct reachable 9s) of every trailhead when each step climbs by `step`
+  pub fn scores_by_trailhead(&self, step: u8) -> HashMap<Position, usize> {
     self
       .find_trailheads()
-      .iter()
-      .map(|&trailhead| self.calculate_trailhead_score(trailhead))
-      .sum()
+      .into_iter()
+      .map(|trailhead| (trailhead, self.calculate_trailhead_score(trailhead, step)))
+      .collect()
   }
 
-  fn sum_ratings(&self) -> usize {
+  /// Rating (distinct trails to a 9) of every trailhead when each step climbs by `step`
+  pub fn ratings_by_trailhead(&self, step: u8) -> HashMap<Position, usize> {
+    let mut memo = HashMap::new();
     self
       .find_trailheads()
-      .iter()
-      .map(|&trailhead| self.calculate_trailhead_rating(trailhead))
-      .sum()
+      .into_iter()
+      .map(|trailhead| {
+        (
+          trailhead,
+          self.calculate_trailhead_rating(trailhead, step, &mut memo),
+        )
+      })
+      .collect()
+  }
+
+  fn sum_scores(&self, step: u8) -> usize {
+    self.scores_by_trailhead(step).values().sum()
+  }
+
+  fn sum_ratings(&self, step: u8) -> usize {
+    self.ratings_by_trailhead(step).values().sum()
   }
 }
 
-fn solve(input: &str, part: u8) -> usize {
-  let map = TopographicMap::new(input);
+pub(crate) fn solve(input: &str, part: u8) -> Result<usize> {
+  let map = TopographicMap::new(input)?;
   match part {
-    1 => map.sum_scores(),
-    2 => map.sum_ratings(),
+    1 => Ok(map.sum_scores(DEFAULT_STEP)),
+    2 => Ok(map.sum_ratings(DEFAULT_STEP)),
     _ => panic!("Only part 1 or 2."),
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
-  println!("Part 1 result = {}", solve(&input, 1));
-  println!("Part 2 result = {}\n", solve(&input, 2));
+  println!("Part 1 result = {}", solve(&input, 1)?);
+  println!("Part 2 result = {}\n", solve(&input, 2)?);
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day10_simple.txt", "Simple puzzle")?;
   print_result("input/day10_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day10_simple.txt");
+
+  #[test]
+  fn sample_answers() {
+    assert_eq!(solve(SAMPLE, 1).unwrap(), 36);
+    assert_eq!(solve(SAMPLE, 2).unwrap(), 81);
+  }
+
+  #[test]
+  fn memoized_ratings_match_naive_enumeration() {
+    let map = TopographicMap::new(SAMPLE).unwrap();
+    for (trailhead, rating) in map.ratings_by_trailhead(1) {
+      assert_eq!(
+        rating,
+        map.enumerate_trails(trailhead).len(),
+        "{trailhead:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn per_trailhead_values() {
+    let map = TopographicMap::new(SAMPLE).unwrap();
+    let scores = map.scores_by_trailhead(1);
+    let ratings = map.ratings_by_trailhead(1);
+    assert_eq!((scores[&(0, 2)], ratings[&(0, 2)]), (5, 20));
+    assert_eq!(scores.values().sum::<usize>(), map.sum_scores(1));
+    assert_eq!(ratings.values().sum::<usize>(), map.sum_ratings(1));
+  }
+
+  #[test]
+  fn enumerates_each_distinct_trail() {
+    let map = TopographicMap::new("01234\n98765\n.9...").unwrap();
+    let shared = [
+      (0, 0),
+      (0, 1),
+      (0, 2),
+      (0, 3),
+      (0, 4),
+      (1, 4),
+      (1, 3),
+      (1, 2),
+      (1, 1),
+    ];
+    let mut trails = map.enumerate_trails((0, 0));
+    trails.sort();
+    let expected: Vec<Vec<Position>> = [(1, 0), (2, 1)]
+      .into_iter()
+      .map(|nine| shared.iter().copied().chain([nine]).collect())
+      .collect();
+    assert_eq!(trails, expected);
+  }
+
+  #[test]
+  fn iterative_ratings_match_recursive_on_a_tall_grid() {
+    // Every cell is row + col modulo 10, so trails run down and right across 500 rows
+    let input: String = (0..500)
+      .map(|row| {
+        (0..20)
+          .map(|col| char::from(b'0' + ((row + col) % 10) as u8))
+          .collect::<String>()
+          + "\n"
+      })
+      .collect();
+    let map = TopographicMap::new(&input).unwrap();
+    let ratings = map.sum_ratings(1);
+    assert!(ratings > 0);
+    assert_eq!(map.sum_ratings_iterative(), ratings);
+  }
+
+  #[test]
+  fn dots_break_trails() {
+    let map = TopographicMap::new("0123\n.654\n.7..\n.89.\n").unwrap();
+    assert_eq!(map.sum_scores(1), 1);
+    let map = TopographicMap::new("0123\n.6.4\n.7..\n.89.\n\n").unwrap();
+    assert_eq!(map.sum_scores(1), 0);
+  }
+
+  #[test]
+  fn rejects_ragged_and_non_digit_grids() {
+    let error = TopographicMap::new("0123\n456\n").unwrap_err();
+    assert_eq!(error.to_string(), "row 1 has 3 columns, expected 4");
+    let error = TopographicMap::new("01\n2x").unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "invalid character 'x' at row 1, column 1"
+    );
+  }
+
+  #[test]
+  fn steeper_slope_changes_the_counts() {
+    // Only the bottom trail climbs by 1; only the top one climbs by 3, and it forks at the 6
+    let map = TopographicMap::new("0369\n..9.\n0123\n7654\n89..").unwrap();
+    assert_eq!((map.sum_scores(1), map.sum_ratings(1)), (1, 1));
+    assert_eq!((map.sum_scores(3), map.sum_ratings(3)), (2, 2));
+    assert_eq!(map.scores_by_trailhead(3)[&(0, 0)], 2);
+    assert_eq!(map.scores_by_trailhead(3)[&(2, 0)], 0);
+  }
+
+  #[test]
+  fn slope_of_two_never_reaches_a_nine() {
+    // The top row climbs by 2 all the way to 8, but from 0 a +2 slope only visits even
+    // heights, so the trail that a +1 slope completes along the bottom row is lost too
+    let map = TopographicMap::new(
+      "02468
+.....
+01234
+98765",
+    )
+    .unwrap();
+    assert_eq!((map.sum_scores(1), map.sum_ratings(1)), (1, 1));
+    assert_eq!((map.sum_scores(2), map.sum_ratings(2)), (0, 0));
+    assert_eq!(map.scores_by_trailhead(2)[&(0, 0)], 0);
+  }
+}
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use smallvec::{SmallVec, smallvec};
 use std::collections::HashMap;
 use std::fs;
 
@@ -32,14 +33,41 @@ fn split_number(num: u64, digit_count: u32) -> (u64, u64) {
   (num / divisor, num % divisor)
 }
 
+/**
+ * successor stones produced by one blink of a single stone
+ */
+pub type Stones = SmallVec<[u64; 2]>;
+
+/**
+ * applies one blink to a single stone using the AoC rules (the default transformation)
+ */
+pub fn blink_stone(stone: u64) -> Stones {
+  if stone == 0 {
+    // rule 1: 0 becomes 1
+    return smallvec![1];
+  }
+
+  let digit_count = count_digits(stone);
+  if digit_count.is_multiple_of(2) {
+    // rule 2: split even-digit numbers
+    let (left, right) = split_number(stone, digit_count);
+    smallvec![left, right]
+  } else {
+    // rule 3: multiply by 2024
+    smallvec![stone * 2024]
+  }
+}
+
 /**
  * recursively counts stones after given number of blinks with memoization
+ * (u128 so very high blink counts don't wrap)
  */
-fn count_stones_after_blinks(
+fn count_stones_after_blinks<F: Fn(u64) -> Stones>(
   stone: u64,
   blinks_remaining: usize,
-  memo: &mut HashMap<(u64, usize), u64>,
-) -> u64 {
+  memo: &mut HashMap<(u64, usize), u128>,
+  rule: &F,
+) -> u128 {
   // base case: no more blinks
   if blinks_remaining == 0 {
     return 1;
@@ -52,21 +80,10 @@ fn count_stones_after_blinks(
   }
 
   // calculate result based on transformation rules
-  let result = if stone == 0 {
-    // rule 1: 0 becomes 1
-    count_stones_after_blinks(1, blinks_remaining - 1, memo)
-  } else {
-    let digit_count = count_digits(stone);
-    if digit_count % 2 == 0 {
-      // rule 2: split even-digit numbers
-      let (left, right) = split_number(stone, digit_count);
-      count_stones_after_blinks(left, blinks_remaining - 1, memo)
-        + count_stones_after_blinks(right, blinks_remaining - 1, memo)
-    } else {
-      // rule 3: multiply by 2024
-      count_stones_after_blinks(stone * 2024, blinks_remaining - 1, memo)
-    }
-  };
+  let result = rule(stone)
+    .into_iter()
+    .map(|next| count_stones_after_blinks(next, blinks_remaining - 1, memo, rule))
+    .sum();
 
   // store in cache and return
   memo.insert(key, result);
@@ -76,17 +93,181 @@ fn count_stones_after_blinks(
 /**
  * solves the stone transformation problem for given number of blinks
  */
-fn solve_stone_problem(input: &str, blinks: usize) -> u64 {
+pub(crate) fn solve_stone_problem(input: &str, blinks: usize) -> u128 {
+  solve_stone_problem_with_rule(input, blinks, &blink_stone)
+}
+
+/**
+ * same as `solve_stone_problem`, but with a caller-supplied transformation rule
+ *
+ * ```
+ * use aoc2024::days::day11::{Stones, blink_stone, solve_stone_problem_with_rule};
+ *
+ * assert_eq!(solve_stone_problem_with_rule("125 17", 6, &blink_stone), 22);
+ * // every stone splits into two copies of itself
+ * let twin = |stone: u64| Stones::from_slice(&[stone, stone]);
+ * assert_eq!(solve_stone_problem_with_rule("125 17", 3, &twin), 16);
+ * ```
+ */
+pub fn solve_stone_problem_with_rule<F: Fn(u64) -> Stones>(
+  input: &str,
+  blinks: usize,
+  rule: &F,
+) -> u128 {
   let stones = parse_input(input);
   let mut memo = HashMap::new();
 
   stones
     .iter()
-    .map(|&s| count_stones_after_blinks(s, blinks, &mut memo))
+    .map(|&s| count_stones_after_blinks(s, blinks, &mut memo, rule))
     .sum()
 }
 
-fn solve_problem(filepath: &str, kind: &str) -> Result<()> {
+/**
+ * counts how many stones of each value exist after given number of blinks under `rule`
+ */
+pub fn stone_counts_after_blinks<F: Fn(u64) -> Stones>(
+  input: &str,
+  blinks: usize,
+  rule: &F,
+) -> HashMap<u64, u128> {
+  let mut counts: HashMap<u64, u128> = HashMap::new();
+  for stone in parse_input(input) {
+    *counts.entry(stone).or_default() += 1;
+  }
+
+  for _ in 0..blinks {
+    let mut next: HashMap<u64, u128> = HashMap::with_capacity(counts.len());
+    for (stone, count) in counts {
+      for successor in rule(stone) {
+        *next.entry(successor).or_default() += count;
+      }
+    }
+    counts = next;
+  }
+
+  counts
+}
+
+/**
+ * one blink as a sparse transition over every stone value reachable from the seeds:
+ * value at index i becomes the value(s) at `successors[i]`
+ */
+struct BlinkTable {
+  values: Vec<u64>,
+  index: HashMap<u64, usize>,
+  successors: Vec<SmallVec<[usize; 2]>>,
+}
+
+/**
+ * expands the set of reachable stone values once (BFS) and records each value's successors
+ * under `rule`. only finishes if finitely many values are reachable, as with the AoC rules.
+ */
+fn blink_table<F: Fn(u64) -> Stones>(seed_values: &[u64], rule: &F) -> BlinkTable {
+  let mut values = Vec::new();
+  let mut index = HashMap::new();
+  let mut index_of = |stone: u64, values: &mut Vec<u64>| {
+    *index.entry(stone).or_insert_with(|| {
+      values.push(stone);
+      values.len() - 1
+    })
+  };
+
+  for &stone in seed_values {
+    index_of(stone, &mut values);
+  }
+
+  let mut successors = Vec::new();
+  // `values` grows while we scan it, so this visits newly discovered values too
+  let mut next = 0;
+  while next < values.len() {
+    let stone_successors = rule(values[next])
+      .into_iter()
+      .map(|stone| index_of(stone, &mut values))
+      .collect();
+    successors.push(stone_successors);
+    next += 1;
+  }
+
+  BlinkTable {
+    values,
+    index,
+    successors,
+  }
+}
+
+/**
+ * applies n blinks to a count vector indexed like `table.values`
+ */
+fn apply_blinks(mut counts: Vec<u128>, table: &BlinkTable, n: usize) -> Vec<u128> {
+  for _ in 0..n {
+    let mut next = vec![0; counts.len()];
+    for (i, &count) in counts.iter().enumerate() {
+      if count == 0 {
+        continue;
+      }
+      for &successor in &table.successors[i] {
+        next[successor] += count;
+      }
+    }
+    counts = next;
+  }
+  counts
+}
+
+/**
+ * same total as `solve_stone_problem`, but via the precomputed transition table
+ */
+pub fn solve_with_blink_table(input: &str, blinks: usize) -> u128 {
+  let stones = parse_input(input);
+  let table = blink_table(&stones, &blink_stone);
+
+  let mut counts = vec![0; table.values.len()];
+  for stone in &stones {
+    counts[table.index[stone]] += 1;
+  }
+
+  apply_blinks(counts, &table, blinks).iter().sum()
+}
+
+/**
+ * longest evolution we search for a repeating state
+ */
+const MAX_CYCLE_BLINKS: usize = 200;
+
+/**
+ * finds (pre-period, period) of the value-count vector evolving from a single stone under
+ * `rule`, so that blink n >= pre-period has the same counts as blink
+ * pre-period + (n - pre-period) % period. a repeat needs a constant total, so states seen
+ * before the total last changed are dropped. returns None if no repeat shows up within
+ * MAX_CYCLE_BLINKS, which is always the case for the AoC rules since every stone's count
+ * grows without bound.
+ */
+pub fn find_cycle<F: Fn(u64) -> Stones>(stone: u64, rule: &F) -> Option<(usize, usize)> {
+  let table = blink_table(&[stone], rule);
+  let mut counts = vec![0u128; table.values.len()];
+  counts[table.index[&stone]] = 1;
+
+  let mut seen: HashMap<Vec<u128>, usize> = HashMap::new();
+  let mut total = 1u128;
+  for blink in 0..=MAX_CYCLE_BLINKS {
+    if let Some(&start) = seen.get(&counts) {
+      return Some((start, blink - start));
+    }
+    seen.insert(counts.clone(), blink);
+
+    counts = apply_blinks(counts, &table, 1);
+    let next_total = counts.iter().sum();
+    if next_total != total {
+      seen.clear();
+      total = next_total;
+    }
+  }
+
+  None
+}
+
+pub fn solve_problem(filepath: &str, kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
 
   println!("{kind}:");
@@ -98,12 +279,89 @@ fn solve_problem(filepath: &str, kind: &str) -> Result<()> {
     "Part 2 results (75 blinks) = {}",
     solve_stone_problem(&input, 75)
   );
+  println!(
+    "Distinct stone values after 75 blinks = {}",
+    stone_counts_after_blinks(&input, 75, &blink_stone).len()
+  );
 
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   solve_problem("input/day11_simple.txt", "Simple puzzle input")?;
   solve_problem("input/day11_full.txt", "Full puzzle input")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day11_simple.txt");
+
+  #[test]
+  fn sample_answers() {
+    assert_eq!(solve_stone_problem(SAMPLE, 6), 22);
+    assert_eq!(solve_stone_problem(SAMPLE, 25), 55312);
+  }
+
+  #[test]
+  fn counts_by_value() {
+    // 2097446912 14168 4048 2 0 2 4 40 48 2024 40 48 80 96 2 8 6 7 6 0 3 2
+    let counts = stone_counts_after_blinks(SAMPLE, 6, &blink_stone);
+    assert_eq!(counts.len(), 15);
+    assert_eq!(counts.values().sum::<u128>(), 22);
+    assert_eq!(
+      (counts[&2], counts[&0], counts[&40], counts[&7]),
+      (4, 2, 2, 1)
+    );
+  }
+
+  #[test]
+  fn counts_past_u64_without_wrapping() {
+    let total = solve_stone_problem("0", 150);
+    assert!(total > u64::MAX as u128);
+    assert_eq!(solve_with_blink_table("0", 150), total);
+  }
+
+  #[test]
+  fn blink_table_matches_the_recursion() {
+    for blinks in [0, 1, 25, 75] {
+      assert_eq!(
+        solve_with_blink_table(SAMPLE, blinks),
+        solve_stone_problem(SAMPLE, blinks)
+      );
+    }
+  }
+
+  #[test]
+  fn custom_rules_apply_to_every_counter() {
+    let triple = |stone: u64| -> Stones { smallvec![stone * 3] };
+    assert_eq!(solve_stone_problem_with_rule("1 2", 3, &triple), 2);
+    assert_eq!(
+      stone_counts_after_blinks("1 2", 3, &triple),
+      HashMap::from([(27, 1), (54, 1)])
+    );
+
+    // Every stone doubles in number, and 0 and 1 swap, so only two values are ever reachable
+    let split = |stone: u64| -> Stones { smallvec![1 - stone, 1 - stone] };
+    assert_eq!(solve_stone_problem_with_rule("0", 10, &split), 1024);
+    assert_eq!(
+      stone_counts_after_blinks("0", 10, &split),
+      HashMap::from([(0, 1024)])
+    );
+    assert_eq!(blink_table(&[0], &split).values, [0, 1]);
+  }
+
+  #[test]
+  fn finds_cycles_under_a_cycling_rule() {
+    let rotate = |stone: u64| -> Stones { smallvec![(stone + 1) % 3] };
+    assert_eq!(find_cycle(0, &rotate), Some((0, 3)));
+
+    // 5 falls into the 0 <-> 1 loop after one blink
+    let flip = |stone: u64| -> Stones { smallvec![if stone >= 2 { 0 } else { 1 - stone }] };
+    assert_eq!(find_cycle(5, &flip), Some((1, 2)));
+
+    assert_eq!(find_cycle(0, &blink_stone), None);
+  }
+}
@@ -1,22 +1,52 @@
-use anyhow::Result;
-use std::collections::{HashSet, VecDeque};
+use anyhow::{Result, bail};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 
+/// Which neighbouring cells join a region: edge-sharing only, or diagonals too
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Connectivity {
+  #[default]
+  Four,
+  Eight,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Point {
-  row: usize,
-  col: usize,
+pub struct Point {
+  pub row: usize,
+  pub col: usize,
 }
 
 impl Point {
-  const fn new(row: usize, col: usize) -> Self {
+  pub const fn new(row: usize, col: usize) -> Self {
     Self { row, col }
   }
 
   fn neighbors(self, rows: usize, cols: usize) -> impl Iterator<Item = Point> {
-    const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+    self.neighbors_with(rows, cols, Connectivity::Four)
+  }
 
-    DIRECTIONS.into_iter().filter_map(move |(dr, dc)| {
+  fn neighbors_with(
+    self,
+    rows: usize,
+    cols: usize,
+    connectivity: Connectivity,
+  ) -> impl Iterator<Item = Point> {
+    const DIRECTIONS: [(isize, isize); 8] = [
+      (0, 1),
+      (1, 0),
+      (0, -1),
+      (-1, 0),
+      (1, 1),
+      (1, -1),
+      (-1, 1),
+      (-1, -1),
+    ];
+    let count = match connectivity {
+      Connectivity::Four => 4,
+      Connectivity::Eight => 8,
+    };
+
+    DIRECTIONS[..count].iter().filter_map(move |&(dr, dc)| {
       let new_row = self.row.wrapping_add_signed(dr);
       let new_col = self.col.wrapping_add_signed(dc);
 
@@ -29,8 +59,9 @@ impl Point {
   }
 }
 
+/// A connected patch of a single plant type, with its fence measurements
 #[derive(Debug)]
-struct Region {
+pub struct Region {
   cells: HashSet<Point>,
   area: usize,
   perimeter: usize,
@@ -49,7 +80,7 @@ impl Region {
 
   fn calculate_perimeter(&mut self, grid: &[Vec<char>]) {
     let rows = grid.len();
-    let cols = grid[0].len();
+    let cols = grid.first().map_or(0, Vec::len);
 
     self.perimeter = self
       .cells
@@ -65,7 +96,7 @@ impl Region {
 
   fn calculate_sides(&mut self, grid: &[Vec<char>]) {
     let rows = grid.len();
-    let cols = grid[0].len();
+    let cols = grid.first().map_or(0, Vec::len);
 
     // For each cell, count corners
     // A corner exists when:
@@ -118,36 +149,119 @@ impl Region {
     }
   }
 
+  pub fn cells(&self) -> &HashSet<Point> {
+    &self.cells
+  }
+
+  pub fn area(&self) -> usize {
+    self.area
+  }
+
+  pub fn perimeter(&self) -> usize {
+    self.perimeter
+  }
+
+  pub fn sides(&self) -> usize {
+    self.sides
+  }
+
   fn multiply_area_by_perimeter(&self) -> usize {
-    self.area * self.perimeter
+    self.area() * self.perimeter()
   }
 
   fn multiply_area_by_sides(&self) -> usize {
-    self.area * self.sides
+    self.area() * self.sides()
   }
 }
 
-struct GardenMap {
+/// Union-find over flat cell indices, with path halving and union by size
+struct DisjointSet {
+  parent: Vec<usize>,
+  size: Vec<usize>,
+}
+
+impl DisjointSet {
+  fn new(n: usize) -> Self {
+    Self {
+      parent: (0..n).collect(),
+      size: vec![1; n],
+    }
+  }
+
+  fn find(&mut self, mut x: usize) -> usize {
+    while self.parent[x] != x {
+      self.parent[x] = self.parent[self.parent[x]];
+      x = self.parent[x];
+    }
+    x
+  }
+
+  fn union(&mut self, a: usize, b: usize) {
+    let (mut a, mut b) = (self.find(a), self.find(b));
+    if a == b {
+      return;
+    }
+    if self.size[a] < self.size[b] {
+      std::mem::swap(&mut a, &mut b);
+    }
+    self.parent[b] = a;
+    self.size[a] += self.size[b];
+  }
+}
+
+/// Plot map split into regions, in reading order of each region's first cell
+///
+/// ```
+/// use aoc2024::days::day12::{GardenMap, Region};
+///
+/// let garden = GardenMap::new("AAAA\nBBCD\nBBCC\nEEEC").unwrap();
+/// let areas: Vec<usize> = garden.regions().iter().map(Region::area).collect();
+/// assert_eq!(areas, [4, 4, 4, 1, 3]);
+/// ```
+pub struct GardenMap {
   grid: Vec<Vec<char>>,
   regions: Vec<Region>,
+  region_index: HashMap<Point, usize>, // cell -> index into `regions`
 }
 
 impl GardenMap {
-  fn new(input: &str) -> Self {
-    let grid: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+  /// Parses the plot map; empty input yields a garden with no regions
+  pub fn new(input: &str) -> Result<Self> {
+    Self::with_connectivity(input, Connectivity::default())
+  }
+
+  /// Like `new`, but regions are grown with the given connectivity. Fences always run along
+  /// cell edges, so perimeter and sides are still measured against the 4 orthogonal neighbours;
+  /// cells that only touch diagonally each keep their own fence around the shared corner.
+  pub fn with_connectivity(input: &str, connectivity: Connectivity) -> Result<Self> {
+    let grid: Vec<Vec<char>> = input
+      .lines()
+      .filter(|line| !line.trim().is_empty())
+      .map(|line| line.chars().collect())
+      .collect();
+
+    let cols = grid.first().map_or(0, Vec::len);
+    if let Some(row) = grid.iter().position(|line| line.len() != cols) {
+      bail!("row {row} has {} columns, expected {cols}", grid[row].len());
+    }
 
     let mut garden = Self {
       grid,
       regions: Vec::new(),
+      region_index: HashMap::new(),
     };
 
-    garden.find_regions();
-    garden
+    garden.find_regions(connectivity);
+    Ok(garden)
   }
 
-  fn find_regions(&mut self) {
+  fn cols(&self) -> usize {
+    self.grid.first().map_or(0, Vec::len)
+  }
+
+  fn find_regions(&mut self, connectivity: Connectivity) {
     let rows = self.grid.len();
-    let cols = self.grid[0].len();
+    let cols = self.cols();
     let mut visited = vec![vec![false; cols]; rows];
 
     for row in 0..rows {
@@ -157,9 +271,18 @@ impl GardenMap {
           let plant_type = self.grid[row][col];
 
           let mut region = Region::new();
-          self.flood_fill(start_point, plant_type, &mut visited, &mut region);
+          self.flood_fill(
+            start_point,
+            plant_type,
+            connectivity,
+            &mut visited,
+            &mut region,
+          );
 
           region.area = region.cells.len();
+          for &cell in &region.cells {
+            self.region_index.insert(cell, self.regions.len());
+          }
           region.calculate_perimeter(&self.grid);
           region.calculate_sides(&self.grid);
 
@@ -173,11 +296,12 @@ impl GardenMap {
     &self,
     start: Point,
     plant_type: char,
+    connectivity: Connectivity,
     visited: &mut [Vec<bool>],
     region: &mut Region,
   ) {
     let rows = self.grid.len();
-    let cols = self.grid[0].len();
+    let cols = self.cols();
     let mut queue = VecDeque::new();
 
     queue.push_back(start);
@@ -185,7 +309,7 @@ impl GardenMap {
     region.cells.insert(start);
 
     while let Some(current) = queue.pop_front() {
-      for neighbor in current.neighbors(rows, cols) {
+      for neighbor in current.neighbors_with(rows, cols, connectivity) {
         if !visited[neighbor.row][neighbor.col]
           && self.grid[neighbor.row][neighbor.col] == plant_type
         {
@@ -197,9 +321,123 @@ impl GardenMap {
     }
   }
 
+  pub fn regions(&self) -> &[Region] {
+    &self.regions
+  }
+
+  /// The region containing `point`, or `None` if it lies outside the map
+  pub fn region_at(&self, point: Point) -> Option<&Region> {
+    self
+      .region_index
+      .get(&point)
+      .map(|&index| &self.regions[index])
+  }
+
+  /// Alternative to the flood fill: union equal-plant neighbours in one pass over the grid,
+  /// then aggregate area and perimeter per set root. Regions come out in the same order
+  /// (by first cell in reading order) and with the same stats as `regions()`.
+  pub fn find_regions_union_find(&self) -> Vec<Region> {
+    let rows = self.grid.len();
+    let cols = self.cols();
+    let mut sets = DisjointSet::new(rows * cols);
+
+    for row in 0..rows {
+      for col in 0..cols {
+        // Right and down neighbours are enough to see every adjacent pair once
+        if col + 1 < cols && self.grid[row][col] == self.grid[row][col + 1] {
+          sets.union(row * cols + col, row * cols + col + 1);
+        }
+        if row + 1 < rows && self.grid[row][col] == self.grid[row + 1][col] {
+          sets.union(row * cols + col, (row + 1) * cols + col);
+        }
+      }
+    }
+
+    let mut region_of_root: HashMap<usize, usize> = HashMap::new();
+    let mut regions: Vec<Region> = Vec::new();
+    for row in 0..rows {
+      for col in 0..cols {
+        let point = Point::new(row, col);
+        let root = sets.find(row * cols + col);
+        let index = *region_of_root.entry(root).or_insert_with(|| {
+          regions.push(Region::new());
+          regions.len() - 1
+        });
+
+        let same_plant_neighbors = point
+          .neighbors(rows, cols)
+          .filter(|n| self.grid[n.row][n.col] == self.grid[row][col])
+          .count();
+
+        let region = &mut regions[index];
+        region.cells.insert(point);
+        region.area += 1;
+        region.perimeter += 4 - same_plant_neighbors;
+      }
+    }
+
+    for region in &mut regions {
+      region.calculate_sides(&self.grid);
+    }
+
+    regions
+  }
+
+  /// Number of fully enclosed pockets (of any other plants) inside `region`.
+  /// Flood-fills the non-region cells from the border of the region's bounding box,
+  /// padded by one cell; whatever stays unreached is enclosed, counted per 4-connected component.
+  pub fn hole_count(&self, region: &Region) -> usize {
+    let Some(min_row) = region.cells.iter().map(|p| p.row).min() else {
+      return 0;
+    };
+    let max_row = region.cells.iter().map(|p| p.row).max().unwrap();
+    let min_col = region.cells.iter().map(|p| p.col).min().unwrap();
+    let max_col = region.cells.iter().map(|p| p.col).max().unwrap();
+
+    // Local box coordinates with a one-cell margin so the outside is always connected
+    let rows = max_row - min_row + 3;
+    let cols = max_col - min_col + 3;
+    let in_region = |p: Point| {
+      p.row > 0
+        && p.col > 0
+        && region
+          .cells
+          .contains(&Point::new(p.row + min_row - 1, p.col + min_col - 1))
+    };
+
+    let mut visited = vec![vec![false; cols]; rows];
+    let fill = |start: Point, visited: &mut [Vec<bool>]| {
+      let mut queue = VecDeque::from([start]);
+      visited[start.row][start.col] = true;
+      while let Some(current) = queue.pop_front() {
+        for neighbor in current.neighbors(rows, cols) {
+          if !visited[neighbor.row][neighbor.col] && !in_region(neighbor) {
+            visited[neighbor.row][neighbor.col] = true;
+            queue.push_back(neighbor);
+          }
+        }
+      }
+    };
+
+    fill(Point::new(0, 0), &mut visited);
+
+    let mut holes = 0;
+    for row in 0..rows {
+      for col in 0..cols {
+        let point = Point::new(row, col);
+        if !visited[row][col] && !in_region(point) {
+          holes += 1;
+          fill(point, &mut visited);
+        }
+      }
+    }
+
+    holes
+  }
+
   fn calculate_total_price(&self) -> usize {
     self
-      .regions
+      .regions()
       .iter()
       .map(|region| region.multiply_area_by_perimeter())
       .sum()
@@ -207,32 +445,130 @@ impl GardenMap {
 
   fn calculate_total_price_under_bulk_discount(&self) -> usize {
     self
-      .regions
+      .regions()
       .iter()
       .map(|region| region.multiply_area_by_sides())
       .sum()
   }
 }
 
-fn solve(input: &str, part: u8) -> usize {
-  let garden = GardenMap::new(input);
+pub(crate) fn solve(input: &str, part: u8) -> Result<usize> {
+  let garden = GardenMap::new(input)?;
   match part {
-    1 => garden.calculate_total_price(),
-    2 => garden.calculate_total_price_under_bulk_discount(),
+    1 => Ok(garden.calculate_total_price()),
+    2 => Ok(garden.calculate_total_price_under_bulk_discount()),
     _ => panic!("Only part 1 or 2 is available."),
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
-  println!("Part 1 result = {}", solve(&input, 1));
-  println!("Part 2 result = {}\n", solve(&input, 2));
+  println!("Part 1 result = {}", solve(&input, 1)?);
+  println!("Part 2 result = {}", solve(&input, 2)?);
+  println!("Regions = {}", GardenMap::new(&input)?.regions().len());
+  println!(
+    "Regions (8-connected) = {}\n",
+    GardenMap::with_connectivity(&input, Connectivity::Eight)?
+      .regions()
+      .len()
+  );
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day12_simple.txt", "Simple puzzle")?;
   print_result("input/day12_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day12_simple.txt");
+
+  #[test]
+  fn sample_answers() {
+    assert_eq!(solve(SAMPLE, 1).unwrap(), 1930);
+    assert_eq!(solve(SAMPLE, 2).unwrap(), 1206);
+  }
+
+  #[test]
+  fn diagonal_connectivity_merges_regions() {
+    let four = GardenMap::new("AB\nBA").unwrap();
+    assert_eq!(four.regions().len(), 4);
+
+    let eight = GardenMap::with_connectivity("AB\nBA", Connectivity::Eight).unwrap();
+    let stats: Vec<_> = eight
+      .regions()
+      .iter()
+      .map(|region| (region.area(), region.perimeter(), region.sides()))
+      .collect();
+    assert_eq!(stats, [(2, 8, 8), (2, 8, 8)]);
+  }
+
+  #[test]
+  fn looks_up_the_region_of_a_cell() {
+    let garden = GardenMap::new(SAMPLE).unwrap();
+    let region = garden.region_at(Point::new(0, 0)).unwrap();
+    assert_eq!((region.area(), region.perimeter()), (12, 18));
+    assert!(region.cells().contains(&Point::new(2, 4)));
+    assert!(garden.region_at(Point::new(10, 0)).is_none());
+  }
+
+  #[test]
+  fn counts_enclosed_holes() {
+    let donut = GardenMap::new("AAA\nABA\nAAA").unwrap();
+    let ring = donut.region_at(Point::new(0, 0)).unwrap();
+    assert_eq!(donut.hole_count(ring), 1);
+    let center = donut.region_at(Point::new(1, 1)).unwrap();
+    assert_eq!(donut.hole_count(center), 0);
+
+    let garden = GardenMap::new("AAAAA\nABACA\nAAAAA\nDDDDD").unwrap();
+    assert_eq!(
+      garden.hole_count(garden.region_at(Point::new(0, 0)).unwrap()),
+      2
+    );
+
+    // Open to the edge of the map, so not a hole
+    let garden = GardenMap::new("AAA\nAB.\nAAA").unwrap();
+    assert_eq!(
+      garden.hole_count(garden.region_at(Point::new(0, 0)).unwrap()),
+      0
+    );
+  }
+
+  #[test]
+  fn union_find_regions_match_the_flood_fill() {
+    let garden = GardenMap::new(SAMPLE).unwrap();
+    let stats = |regions: &[Region]| -> Vec<_> {
+      regions
+        .iter()
+        .map(|region| (region.area(), region.perimeter(), region.sides()))
+        .collect()
+    };
+    assert_eq!(
+      stats(&garden.find_regions_union_find()),
+      stats(garden.regions())
+    );
+  }
+
+  #[test]
+  fn empty_and_blank_input_have_no_regions() {
+    assert!(GardenMap::new("").unwrap().regions().is_empty());
+    assert!(GardenMap::new("\n\n").unwrap().regions().is_empty());
+  }
+
+  #[test]
+  fn trailing_blank_line_is_not_a_row() {
+    let garden = GardenMap::new("AAB\nABB\n\n").unwrap();
+    assert_eq!(garden.regions().len(), 2);
+  }
+
+  #[test]
+  fn rejects_ragged_rows() {
+    let error = GardenMap::new("AAB\nAB\n").err().unwrap();
+    assert_eq!(error.to_string(), "row 1 has 2 columns, expected 3");
+  }
+}
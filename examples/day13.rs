@@ -1,18 +1,53 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use regex::Regex;
 use std::fs;
 
 #[derive(Debug, Clone, Copy)]
-struct ClawMachine {
-  button_a: (i64, i64), // (dx, dy)
-  button_b: (i64, i64), // (dx, dy)
-  prize: (i64, i64),    // (x, y)
+pub struct ClawMachine {
+  pub button_a: (i64, i64), // (dx, dy)
+  pub button_b: (i64, i64), // (dx, dy)
+  pub prize: (i64, i64),    // (x, y)
+}
+
+/// Tokens spent per button press; the puzzle's are 3 for A and 1 for B
+#[derive(Debug, Clone, Copy)]
+pub struct TokenCosts {
+  pub a: i64,
+  pub b: i64,
+}
+
+impl Default for TokenCosts {
+  fn default() -> Self {
+    Self { a: 3, b: 1 }
+  }
 }
 
 impl ClawMachine {
-  fn solve(&self, max_presses: Option<i64>) -> Option<i64> {
+  pub fn solve(&self, max_presses: Option<i64>, costs: TokenCosts) -> Option<i64> {
+    self
+      .solve_presses(max_presses, costs)
+      .map(|(_, _, cost)| cost)
+  }
+
+  /// Buttons move the claw along the same line (zero determinant), so Cramer's rule doesn't apply
+  pub fn is_degenerate(&self) -> bool {
     let (ax, ay) = self.button_a;
     let (bx, by) = self.button_b;
-    let (px, py) = self.prize;
+    ax as i128 * by as i128 == ay as i128 * bx as i128
+  }
+
+  /// Winning combination as (A presses, B presses, token cost), if any.
+  /// With independent buttons the integer solution is unique, so `costs` only affects the
+  /// returned total; for degenerate machines they pick the cheapest of many combinations.
+  pub fn solve_presses(
+    &self,
+    max_presses: Option<i64>,
+    costs: TokenCosts,
+  ) -> Option<(i64, i64, i64)> {
+    // Widen to i128: products like px * by overflow i64 for large prizes and deltas
+    let (ax, ay) = (self.button_a.0 as i128, self.button_a.1 as i128);
+    let (bx, by) = (self.button_b.0 as i128, self.button_b.1 as i128);
+    let (px, py) = (self.prize.0 as i128, self.prize.1 as i128);
 
     // System of equations:
     // a * ax + b * bx = px
@@ -23,11 +58,12 @@ impl ClawMachine {
     // a = (px * by - py * bx) / determinant
     // b = (ax * py - ay * px) / determinant
 
-    let determinant = ax * by - ay * bx;
-    if determinant == 0 {
-      return None; // No unique solution
+    if self.is_degenerate() {
+      return self.solve_degenerate(max_presses, costs);
     }
 
+    let determinant = ax * by - ay * bx;
+
     let numerator_a = px * by - py * bx;
     let numerator_b = ax * py - ay * px;
 
@@ -45,132 +81,392 @@ impl ClawMachine {
     }
 
     // Check max presses constraint if specified
-    if let Some(max) = max_presses {
-      if a > max || b > max {
-        return None;
-      }
+    if let Some(max) = max_presses.map(i128::from)
+      && (a > max || b > max)
+    {
+      return None;
     }
 
     // Verify solution (double-check)
-    if a * ax + b * bx == px && a * ay + b * by == py {
-      Some(3 * a + b) // Cost: 3 tokens per A press, 1 per B press
+    if a * ax + b * bx != px || a * ay + b * by != py {
+      return None;
+    }
+
+    let cost = costs.a as i128 * a + costs.b as i128 * b;
+    Some((
+      i64::try_from(a).ok()?,
+      i64::try_from(b).ok()?,
+      i64::try_from(cost).ok()?,
+    ))
+  }
+
+  /// Collinear buttons: the prize must lie on their common line, after which both equations
+  /// collapse to one, `a * u + b * v = w`. Its integer solutions form a 1D family
+  ///   a = a0 + k * (v / g),  b = b0 - k * (u / g)
+  /// and the cost is linear in k, so the cheapest valid combination sits at an end of the
+  /// feasible k range.
+  fn solve_degenerate(
+    &self,
+    max_presses: Option<i64>,
+    costs: TokenCosts,
+  ) -> Option<(i64, i64, i64)> {
+    let (ax, ay) = (self.button_a.0 as i128, self.button_a.1 as i128);
+    let (bx, by) = (self.button_b.0 as i128, self.button_b.1 as i128);
+    let (px, py) = (self.prize.0 as i128, self.prize.1 as i128);
+
+    // Prize must be on the buttons' line (or at the origin if neither button moves)
+    let on_line = if (ax, ay) != (0, 0) {
+      ax * py == ay * px
+    } else if (bx, by) != (0, 0) {
+      bx * py == by * px
     } else {
-      None
+      (px, py) == (0, 0)
+    };
+    if !on_line {
+      return None;
     }
+
+    // Work along whichever axis the buttons actually move on
+    let (u, v, w) = if ax != 0 || bx != 0 {
+      (ax, bx, px)
+    } else {
+      (ay, by, py)
+    };
+
+    let (a, b) = if u == 0 && v == 0 {
+      (0, 0)
+    } else {
+      let (g, x0, y0) = extended_gcd(u, v);
+      if w % g != 0 {
+        return None;
+      }
+      let (a0, b0) = (x0 * (w / g), y0 * (w / g));
+      let (step_a, step_b) = (v / g, -(u / g));
+
+      // Intersect the k ranges that keep each press count within [0, max]
+      let max = max_presses.map(i128::from);
+      let mut k_range = (None, None);
+      for (base, step) in [(a0, step_a), (b0, step_b)] {
+        k_range = intersect_k_range(k_range, base, step, max)?;
+      }
+
+      let slope = costs.a as i128 * step_a + costs.b as i128 * step_b;
+      let k = match (slope.signum(), k_range) {
+        (1, (Some(lo), _)) | (0, (Some(lo), _)) => lo,
+        (-1, (_, Some(hi))) | (0, (None, Some(hi))) => hi,
+        _ => return None, // cost unbounded below
+      };
+      (a0 + k * step_a, b0 + k * step_b)
+    };
+
+    if a * ax + b * bx != px || a * ay + b * by != py {
+      return None;
+    }
+
+    let cost = costs.a as i128 * a + costs.b as i128 * b;
+    Some((
+      i64::try_from(a).ok()?,
+      i64::try_from(b).ok()?,
+      i64::try_from(cost).ok()?,
+    ))
+  }
+}
+
+/// (g, x, y) with u * x + v * y = g = gcd(u, v) > 0, for (u, v) != (0, 0)
+fn extended_gcd(u: i128, v: i128) -> (i128, i128, i128) {
+  if v == 0 {
+    (u.abs(), u.signum(), 0)
+  } else {
+    let (g, x, y) = extended_gcd(v, u.rem_euclid(v));
+    (g, y, x - u.div_euclid(v) * y)
   }
 }
 
-fn parse_input(input: &str) -> Vec<ClawMachine> {
-  let mut machines = Vec::new();
-  let lines: Vec<&str> = input.trim().lines().collect();
+/// Narrow `range` (inclusive, `None` = unbounded) to the k where `0 <= base + k * step <= max`.
+/// Returns `None` when no k is feasible.
+fn intersect_k_range(
+  range: (Option<i128>, Option<i128>),
+  base: i128,
+  step: i128,
+  max: Option<i128>,
+) -> Option<(Option<i128>, Option<i128>)> {
+  let (mut lo, mut hi) = range;
+  let tighten_lo = |lo: Option<i128>, bound: i128| Some(lo.map_or(bound, |lo| lo.max(bound)));
+  let tighten_hi = |hi: Option<i128>, bound: i128| Some(hi.map_or(bound, |hi| hi.min(bound)));
 
-  let mut i = 0;
-  while i < lines.len() {
-    if lines[i].trim().is_empty() {
-      i += 1;
-      continue;
+  if step == 0 {
+    if base < 0 || max.is_some_and(|max| base > max) {
+      return None;
+    }
+  } else if step > 0 {
+    lo = tighten_lo(lo, div_ceil(-base, step));
+    if let Some(max) = max {
+      hi = tighten_hi(hi, div_floor(max - base, step));
+    }
+  } else {
+    hi = tighten_hi(hi, div_floor(-base, step));
+    if let Some(max) = max {
+      lo = tighten_lo(lo, div_ceil(max - base, step));
     }
+  }
 
-    // Parse Button A line: "Button A: X+94, Y+34"
-    let button_a_line = lines[i];
-    let button_a_parts: Vec<&str> = button_a_line
-      .strip_prefix("Button A: ")
-      .unwrap()
-      .split(", ")
-      .collect();
-    let ax: i64 = button_a_parts[0]
-      .strip_prefix("X+")
-      .unwrap()
-      .parse()
-      .unwrap();
-    let ay: i64 = button_a_parts[1]
-      .strip_prefix("Y+")
-      .unwrap()
-      .parse()
-      .unwrap();
-
-    // Parse Button B line: "Button B: X+22, Y+67"
-    let button_b_line = lines[i + 1];
-    let button_b_parts: Vec<&str> = button_b_line
-      .strip_prefix("Button B: ")
-      .unwrap()
-      .split(", ")
-      .collect();
-    let bx: i64 = button_b_parts[0]
-      .strip_prefix("X+")
-      .unwrap()
-      .parse()
-      .unwrap();
-    let by: i64 = button_b_parts[1]
-      .strip_prefix("Y+")
-      .unwrap()
-      .parse()
-      .unwrap();
-
-    // Parse Prize line: "Prize: X=8400, Y=5400"
-    let prize_line = lines[i + 2];
-    let prize_parts: Vec<&str> = prize_line
-      .strip_prefix("Prize: ")
-      .unwrap()
-      .split(", ")
-      .collect();
-    let px: i64 = prize_parts[0].strip_prefix("X=").unwrap().parse().unwrap();
-    let py: i64 = prize_parts[1].strip_prefix("Y=").unwrap().parse().unwrap();
-
-    machines.push(ClawMachine {
-      button_a: (ax, ay),
-      button_b: (bx, by),
-      prize: (px, py),
-    });
-
-    i += 3;
+  match (lo, hi) {
+    (Some(lo), Some(hi)) if lo > hi => None,
+    _ => Some((lo, hi)),
   }
+}
 
-  machines
+fn div_floor(n: i128, d: i128) -> i128 {
+  let q = n / d;
+  if n % d != 0 && (n < 0) != (d < 0) {
+    q - 1
+  } else {
+    q
+  }
+}
+
+fn div_ceil(n: i128, d: i128) -> i128 {
+  -div_floor(-n, d)
+}
+
+pub fn parse_input(input: &str) -> Result<Vec<ClawMachine>> {
+  // One machine per blank-line separated block, e.g.
+  //   Button A: X+94, Y+34
+  //   Button B: X+22, Y-67
+  //   Prize: X=8400, Y=5400
+  let re = Regex::new(
+    r"^\s*Button\s*A\s*:\s*X\s*([+-]?\s*\d+)\s*,\s*Y\s*([+-]?\s*\d+)\s+Button\s*B\s*:\s*X\s*([+-]?\s*\d+)\s*,\s*Y\s*([+-]?\s*\d+)\s+Prize\s*:\s*X\s*=\s*([+-]?\s*\d+)\s*,\s*Y\s*=\s*([+-]?\s*\d+)\s*$",
+  )
+  .unwrap();
+
+  let mut blocks: Vec<Vec<&str>> = vec![Vec::new()];
+  for line in input.lines() {
+    if line.trim().is_empty() {
+      blocks.push(Vec::new());
+    } else {
+      blocks.last_mut().unwrap().push(line);
+    }
+  }
+
+  blocks
+    .iter()
+    .filter(|block| !block.is_empty())
+    .enumerate()
+    .map(|(index, block)| {
+      let text = block.join("\n");
+      let caps = re
+        .captures(&text)
+        .with_context(|| format!("malformed machine #{}:\n{text}", index + 1))?;
+      let num = |i: usize| -> Result<i64> {
+        let digits: String = caps[i].chars().filter(|c| !c.is_whitespace()).collect();
+        Ok(digits.parse()?)
+      };
+
+      Ok(ClawMachine {
+        button_a: (num(1)?, num(2)?),
+        button_b: (num(3)?, num(4)?),
+        prize: (num(5)?, num(6)?),
+      })
+    })
+    .collect()
 }
 
 fn minimize_tokens_to_win_prizes(machines: &[ClawMachine]) -> i64 {
   machines
     .iter()
-    .filter_map(|machine| machine.solve(Some(100)))
+    .filter_map(|machine| machine.solve(Some(100), TokenCosts::default()))
     .sum()
 }
 
-fn minimize_tokens_to_win_prizes_with_modified_positions(machines: &[ClawMachine]) -> i64 {
-  // Part 2: Add 10000000000000 to prize coordinates and no button press limit
+/// Part 2 shift applied to both prize coordinates
+const PRIZE_OFFSET: i64 = 10000000000000;
+
+fn minimize_tokens_to_win_prizes_with_modified_positions(
+  machines: &[ClawMachine],
+  offset: i64,
+) -> i64 {
+  // Part 2: Add `offset` to prize coordinates and no button press limit
   machines
     .iter()
     .map(|machine| ClawMachine {
       button_a: machine.button_a,
       button_b: machine.button_b,
-      prize: (
-        machine.prize.0 + 10000000000000,
-        machine.prize.1 + 10000000000000,
-      ),
+      prize: (machine.prize.0 + offset, machine.prize.1 + offset),
     })
-    .filter_map(|machine| machine.solve(None))
+    .filter_map(|machine| machine.solve(None, TokenCosts::default()))
     .sum()
 }
 
-fn solve(input: &str, part: u8) -> i64 {
-  let machines = parse_input(input);
+pub(crate) fn solve(input: &str, part: u8) -> Result<i64> {
+  let machines = parse_input(input)?;
   match part {
-    1 => minimize_tokens_to_win_prizes(&machines),
-    2 => minimize_tokens_to_win_prizes_with_modified_positions(&machines),
+    1 => Ok(minimize_tokens_to_win_prizes(&machines)),
+    2 => Ok(minimize_tokens_to_win_prizes_with_modified_positions(
+      &machines,
+      PRIZE_OFFSET,
+    )),
     _ => panic!("Only part 1 or 2 is possible."),
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
-  println!("Part 1 result = {}", solve(&input, 1));
-  println!("Part 2 result = {}\n", solve(&input, 2));
+  println!("Part 1 result = {}", solve(&input, 1)?);
+  println!("Part 2 result = {}\n", solve(&input, 2)?);
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day13_simple.txt", "Simple puzzle")?;
   print_result("input/day13_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day13_simple.txt");
+
+  #[test]
+  fn sample_answers() {
+    assert_eq!(solve(SAMPLE, 1).unwrap(), 480);
+    assert_eq!(solve(SAMPLE, 2).unwrap(), 875318608908);
+  }
+
+  #[test]
+  fn parses_negative_deltas_and_loose_whitespace() {
+    let machines = parse_input(
+      "Button A: X-3, Y+4\nButton B: X+5, Y-2\nPrize: X=7, Y=6\n\n\n  Button A:X + 1 ,  Y+2\n\tButton B : X+3, Y+4\nPrize:  X = 10,Y=14  \n",
+    )
+    .unwrap();
+    assert_eq!(machines.len(), 2);
+    assert_eq!(
+      (
+        machines[0].button_a,
+        machines[0].button_b,
+        machines[0].prize
+      ),
+      ((-3, 4), (5, -2), (7, 6))
+    );
+    assert_eq!(
+      (
+        machines[1].button_a,
+        machines[1].button_b,
+        machines[1].prize
+      ),
+      ((1, 2), (3, 4), (10, 14))
+    );
+    assert!(parse_input("Button A: X+1\nPrize: X=1, Y=1").is_err());
+  }
+
+  #[test]
+  fn presses_reach_the_prize() {
+    for machine in parse_input(SAMPLE).unwrap() {
+      let Some((a, b, cost)) = machine.solve_presses(None, TokenCosts::default()) else {
+        continue;
+      };
+      assert_eq!(
+        machine.button_a.0 * a + machine.button_b.0 * b,
+        machine.prize.0
+      );
+      assert_eq!(
+        machine.button_a.1 * a + machine.button_b.1 * b,
+        machine.prize.1
+      );
+      assert_eq!(cost, 3 * a + b);
+    }
+  }
+
+  #[test]
+  fn large_coordinates_do_not_overflow() {
+    // px * by is about 4e27 here, far past i64
+    let machine = ClawMachine {
+      button_a: (1_000_000_007, 3),
+      button_b: (5, 1_000_000_009),
+      prize: (4_000_000_043_000_000_000, 3_000_000_039_000_000_000),
+    };
+    assert_eq!(
+      machine.solve_presses(None, TokenCosts::default()),
+      Some((4_000_000_000, 3_000_000_000, 15_000_000_000))
+    );
+  }
+
+  #[test]
+  fn zero_offset_is_part1_without_the_press_limit() {
+    let machines = parse_input(SAMPLE).unwrap();
+    assert_eq!(
+      minimize_tokens_to_win_prizes_with_modified_positions(&machines, 0),
+      minimize_tokens_to_win_prizes(&machines)
+    );
+
+    // Reachable only past 100 presses
+    let machine = ClawMachine {
+      button_a: (1, 0),
+      button_b: (0, 1),
+      prize: (150, 5),
+    };
+    assert_eq!(minimize_tokens_to_win_prizes(&[machine]), 0);
+    assert_eq!(
+      minimize_tokens_to_win_prizes_with_modified_positions(&[machine], 0),
+      455
+    );
+  }
+
+  #[test]
+  fn degenerate_machines() {
+    // B moves twice as far as A; 7 = 1 * 1 + 3 * 2 is the cheapest way to reach (7, 14)
+    let machine = ClawMachine {
+      button_a: (1, 2),
+      button_b: (2, 4),
+      prize: (7, 14),
+    };
+    assert!(machine.is_degenerate());
+    assert_eq!(
+      machine.solve_presses(None, TokenCosts::default()),
+      Some((1, 3, 6))
+    );
+
+    // Off the buttons' line
+    let machine = ClawMachine {
+      prize: (7, 15),
+      ..machine
+    };
+    assert_eq!(machine.solve_presses(None, TokenCosts::default()), None);
+  }
+
+  #[test]
+  fn costs_pick_the_cheapest_collinear_combination() {
+    let machine = ClawMachine {
+      button_a: (1, 1),
+      button_b: (2, 2),
+      prize: (4, 4),
+    };
+    assert_eq!(
+      machine.solve_presses(None, TokenCosts::default()),
+      Some((0, 2, 2))
+    );
+    assert_eq!(
+      machine.solve_presses(None, TokenCosts { a: 1, b: 5 }),
+      Some((4, 0, 4))
+    );
+  }
+
+  #[test]
+  fn costs_only_change_the_total_of_a_unique_solution() {
+    let machine = ClawMachine {
+      button_a: (94, 34),
+      button_b: (22, 67),
+      prize: (8400, 5400),
+    };
+    assert_eq!(
+      machine.solve_presses(Some(100), TokenCosts::default()),
+      Some((80, 40, 280))
+    );
+    assert_eq!(
+      machine.solve_presses(Some(100), TokenCosts { a: 1, b: 3 }),
+      Some((80, 40, 200))
+    );
+  }
+}
@@ -1,9 +1,9 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
 use regex::Regex;
 use std::fs;
 
 #[derive(Debug, Clone, Copy)]
-struct Robot {
+pub struct Robot {
   position: (i32, i32),
   velocity: (i32, i32),
 }
@@ -23,7 +23,7 @@ impl Robot {
   }
 }
 
-fn parse_robots(input: &str) -> Vec<Robot> {
+pub fn parse_robots(input: &str) -> Vec<Robot> {
   let re = Regex::new(r"p=(-?\d+),(-?\d+) v=(-?\d+),(-?\d+)").unwrap();
 
   input
@@ -40,15 +40,65 @@ fn parse_robots(input: &str) -> Vec<Robot> {
     .collect()
 }
 
+fn gcd(a: i32, b: i32) -> i32 {
+  if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn lcm(a: i32, b: i32) -> i32 {
+  a / gcd(a, b) * b
+}
+
+/// Robots wrap around the room's edges, which needs at least one cell each way
+fn check_room(width: i32, height: i32) -> Result<()> {
+  if width <= 0 || height <= 0 {
+    bail!("room must be at least 1x1, not {width}x{height}");
+  }
+  Ok(())
+}
+
+/// Smallest t > 0 at which every robot is back at its starting position. Along one axis a
+/// robot repeats every `size / gcd(v mod size, size)` seconds, so the answer is the lcm of
+/// those per-axis periods; it divides lcm(width, height), i.e. width * height when coprime.
+pub fn position_period(robots: &[Robot], width: i32, height: i32) -> Result<i32> {
+  check_room(width, height)?;
+  Ok(robots.iter().fold(1, |period, robot| {
+    let period_x = width / gcd(robot.velocity.0.rem_euclid(width), width);
+    let period_y = height / gcd(robot.velocity.1.rem_euclid(height), height);
+    lcm(period, lcm(period_x, period_y))
+  }))
+}
+
+/// Wrapped (x, y) of every robot after `seconds`, in input order.
+///
+/// ```
+/// use aoc2024::days::day14::{parse_robots, positions_at};
+///
+/// // Starts one cell from the right edge and moves 2 right per second
+/// let robots = parse_robots("p=9,3 v=2,0");
+/// assert_eq!(positions_at(&robots, 11, 7, 1), [(0, 3)]);
+/// ```
+pub fn positions_at(robots: &[Robot], width: i32, height: i32, seconds: i32) -> Vec<(i32, i32)> {
+  robots
+    .iter()
+    .map(|robot| robot.move_after_seconds(seconds, width, height))
+    .collect()
+}
+
 fn calculate_safety_factor(robots: &[Robot], width: i32, height: i32, seconds: i32) -> usize {
+  quadrant_counts(robots, width, height, seconds)
+    .iter()
+    .product()
+}
+
+/// Robots per quadrant as [top_left, top_right, bottom_left, bottom_right];
+/// robots on the middle row or column belong to none
+pub fn quadrant_counts(robots: &[Robot], width: i32, height: i32, seconds: i32) -> [usize; 4] {
   let mid_x = width / 2;
   let mid_y = height / 2;
 
   let mut quadrants = [0; 4]; // [top_left, top_right, bottom_left, bottom_right]
 
-  for robot in robots {
-    let (x, y) = robot.move_after_seconds(seconds, width, height);
-
+  for (x, y) in positions_at(robots, width, height, seconds) {
     // Skip robots exactly in the middle
     if x == mid_x || y == mid_y {
       continue;
@@ -62,14 +112,11 @@ fn calculate_safety_factor(robots: &[Robot], width: i32, height: i32, seconds: i
     }
   }
 
-  quadrants.iter().product()
+  quadrants
 }
 
 fn calculate_position_variance(robots: &[Robot], width: i32, height: i32, seconds: i32) -> f64 {
-  let positions: Vec<(i32, i32)> = robots
-    .iter()
-    .map(|robot| robot.move_after_seconds(seconds, width, height))
-    .collect();
+  let positions = positions_at(robots, width, height, seconds);
 
   if positions.is_empty() {
     return f64::INFINITY;
@@ -96,10 +143,10 @@ fn calculate_position_variance(robots: &[Robot], width: i32, height: i32, second
 
 #[allow(dead_code)]
 fn visualize_robots(robots: &[Robot], width: i32, height: i32, seconds: i32) -> String {
-  let positions: std::collections::HashSet<(i32, i32)> = robots
-    .iter()
-    .map(|robot| robot.move_after_seconds(seconds, width, height))
-    .collect();
+  let positions: std::collections::HashSet<(i32, i32)> =
+    positions_at(robots, width, height, seconds)
+      .into_iter()
+      .collect();
 
   let mut grid = String::new();
   for y in 0..height {
@@ -115,9 +162,28 @@ fn visualize_robots(robots: &[Robot], width: i32, height: i32, seconds: i32) ->
   grid
 }
 
-fn minimize_robot_time_to_display_easter_egg(robots: &[Robot], width: i32, height: i32) -> usize {
-  // The pattern repeats every width * height seconds due to the modular arithmetic
-  let max_seconds = width * height;
+/// Binary P6 PPM of the frame at `seconds`: black background, one white pixel per robot
+pub fn render_ppm(robots: &[Robot], width: i32, height: i32, seconds: i32) -> Vec<u8> {
+  let mut ppm = format!("P6\n{width} {height}\n255\n").into_bytes();
+  let header_len = ppm.len();
+  ppm.resize(header_len + (width * height * 3) as usize, 0);
+
+  for (x, y) in positions_at(robots, width, height, seconds) {
+    let offset = header_len + ((y * width + x) * 3) as usize;
+    ppm[offset..offset + 3].fill(255);
+  }
+
+  ppm
+}
+
+// Alternate to `find_egg_by_clustering`: picks the frame where robots are most bunched up
+pub fn minimize_robot_time_to_display_easter_egg(
+  robots: &[Robot],
+  width: i32,
+  height: i32,
+) -> Result<usize> {
+  // Every frame after this repeats an earlier one
+  let max_seconds = position_period(robots, width, height)?;
 
   let mut min_variance = f64::INFINITY;
   let mut best_seconds = 0;
@@ -131,20 +197,110 @@ fn minimize_robot_time_to_display_easter_egg(robots: &[Robot], width: i32, heigh
     }
   }
 
-  best_seconds as usize
+  Ok(best_seconds as usize)
+}
+
+/// Size of the largest 4-connected cluster of occupied cells at the given time
+fn largest_cluster(robots: &[Robot], width: i32, height: i32, seconds: i32) -> usize {
+  let positions = positions_at(robots, width, height, seconds);
+  let mut occupied = vec![false; (width * height) as usize];
+  for &(x, y) in &positions {
+    occupied[(y * width + x) as usize] = true;
+  }
+
+  // Flood fill from each robot rather than scanning every cell, clearing cells as we go so
+  // each cluster is counted once
+  let mut largest = 0;
+  let mut stack = Vec::new();
+  for (x, y) in positions {
+    if !std::mem::take(&mut occupied[(y * width + x) as usize]) {
+      continue;
+    }
+
+    stack.push((x, y));
+    let mut size = 0;
+    while let Some((x, y)) = stack.pop() {
+      size += 1;
+      for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+        if (0..width).contains(&nx)
+          && (0..height).contains(&ny)
+          && std::mem::take(&mut occupied[(ny * width + nx) as usize])
+        {
+          stack.push((nx, ny));
+        }
+      }
+    }
+    largest = largest.max(size);
+  }
+
+  largest
+}
+
+/// Robots in one cluster that can only be the picture: in the full puzzle its cluster has 229
+/// robots, while no other frame has more than 14 (see `picture_cluster_dwarfs_every_other_frame`)
+const EGG_CLUSTER_SIZE: usize = 100;
+
+/// The easter egg is a filled picture, so look for the frame with the biggest connected
+/// cluster of robots. Stops early once a cluster reaches `threshold`, if given.
+///
+/// Inputs without a picture, like the simple puzzle, have no right answer; this then picks
+/// the first frame with the biggest cluster (6 for the simple puzzle), where the variance
+/// method picks the most bunched-up one (24).
+pub fn find_egg_by_clustering(
+  robots: &[Robot],
+  width: i32,
+  height: i32,
+  threshold: Option<usize>,
+) -> Result<usize> {
+  let max_seconds = position_period(robots, width, height)?;
+
+  let mut best_cluster = 0;
+  let mut best_seconds = 0;
+
+  for seconds in 0..max_seconds {
+    let cluster = largest_cluster(robots, width, height, seconds);
+
+    if cluster > best_cluster {
+      best_cluster = cluster;
+      best_seconds = seconds;
+    }
+
+    if threshold.is_some_and(|threshold| cluster >= threshold) {
+      break;
+    }
+  }
+
+  Ok(best_seconds as usize)
 }
 
-fn solve(input: &str, width: i32, height: i32, part: u8) -> usize {
+/// Default simulation time for the part 1 safety factor
+pub(crate) const SAFETY_SECONDS: i32 = 100;
+
+/// `part1_seconds` is when part 1 takes the safety factor. It applies to part 1 only: part 2
+/// searches every distinct frame for the picture and ignores it.
+pub(crate) fn solve(
+  input: &str,
+  width: i32,
+  height: i32,
+  part1_seconds: i32,
+  part: u8,
+) -> Result<usize> {
+  check_room(width, height)?;
   let robots = parse_robots(input);
 
   match part {
-    1 => calculate_safety_factor(&robots, width, height, 100),
-    2 => minimize_robot_time_to_display_easter_egg(&robots, width, height),
+    1 => Ok(calculate_safety_factor(
+      &robots,
+      width,
+      height,
+      part1_seconds,
+    )),
+    2 => find_egg_by_clustering(&robots, width, height, Some(EGG_CLUSTER_SIZE)),
     _ => panic!("Only part 1 or 2 is possible."),
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   let (width, height) = match puzzle_kind {
     "Simple puzzle" => (11, 7),
@@ -152,13 +308,143 @@ fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
     _ => panic!("Neither simple nor full puzzle."),
   };
   println!("Input: {puzzle_kind}");
-  println!("Part 1 result = {}", solve(&input, width, height, 1));
-  println!("Part 2 result = {}\n", solve(&input, width, height, 2));
+  println!(
+    "Part 1 result = {}",
+    solve(&input, width, height, SAFETY_SECONDS, 1)?
+  );
+  println!(
+    "Part 2 result = {}\n",
+    solve(&input, width, height, SAFETY_SECONDS, 2)?
+  );
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day14_simple.txt", "Simple puzzle")?;
   print_result("input/day14_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day14_simple.txt");
+
+  #[test]
+  fn quadrant_counts_of_the_sample() {
+    let robots = parse_robots(SAMPLE);
+    let counts = quadrant_counts(&robots, 11, 7, SAFETY_SECONDS);
+    assert_eq!(counts, [1, 3, 4, 1]);
+    assert_eq!(
+      counts.iter().product::<usize>(),
+      calculate_safety_factor(&robots, 11, 7, SAFETY_SECONDS)
+    );
+  }
+
+  #[test]
+  fn ppm_has_a_header_and_white_robots() {
+    let robots = parse_robots("p=1,0 v=0,0\np=2,1 v=1,0");
+    let ppm = render_ppm(&robots, 3, 2, 0);
+    let header = b"P6\n3 2\n255\n";
+    assert_eq!(&ppm[..header.len()], header);
+
+    let pixels = &ppm[header.len()..];
+    assert_eq!(pixels.len(), 3 * 2 * 3);
+    let white: Vec<usize> = (0..6)
+      .filter(|&i| pixels[i * 3..i * 3 + 3] == [255; 3])
+      .collect();
+    assert_eq!(white, [1, 5]);
+    assert_eq!(pixels.iter().filter(|&&byte| byte == 0).count(), 4 * 3);
+
+    // A second later the moving robot has wrapped to the left edge
+    let ppm = render_ppm(&robots, 3, 2, 1);
+    assert_eq!(ppm[header.len() + 3 * 3..header.len() + 4 * 3], [255; 3]);
+  }
+
+  /// Robots that fill a 3x3 square at `seconds` and scatter before and after it
+  fn square_at(seconds: i32, width: i32, height: i32) -> Vec<Robot> {
+    (0..9)
+      .map(|i| {
+        let (x, y) = (4 + i % 3, 2 + i / 3);
+        let (vx, vy) = (i + 1, 2 * i + 1);
+        Robot::new(
+          (x - vx * seconds).rem_euclid(width),
+          (y - vy * seconds).rem_euclid(height),
+          vx,
+          vy,
+        )
+      })
+      .collect()
+  }
+
+  #[test]
+  fn seconds_only_apply_to_part1() {
+    let input = include_str!("../input/day14_simple.txt");
+    assert_eq!(solve(input, 11, 7, SAFETY_SECONDS, 1).unwrap(), 12);
+    // By hand: one robot per quadrant at 0 seconds, then the top-right one wraps left
+    let robots = "p=0,0 v=1,1\np=10,0 v=-6,0\np=0,6 v=0,-1\np=10,6 v=0,0\np=4,3 v=1,0";
+    assert_eq!(solve(robots, 11, 7, 0, 1).unwrap(), 1);
+    assert_eq!(solve(robots, 11, 7, 1, 1).unwrap(), 0);
+    assert_eq!(
+      solve(input, 11, 7, 0, 2).unwrap(),
+      solve(input, 11, 7, SAFETY_SECONDS, 2).unwrap()
+    );
+  }
+
+  #[test]
+  fn period_of_coprime_room_is_its_area() {
+    let robots = parse_robots("p=0,0 v=1,1\np=3,2 v=-4,5");
+    assert_eq!(position_period(&robots, 11, 7).unwrap(), 77);
+    // Moving 2 a second in a 4-wide, 2-high room repeats after 2 seconds
+    let robots = parse_robots("p=0,0 v=2,2");
+    assert_eq!(position_period(&robots, 4, 2).unwrap(), 2);
+  }
+
+  #[test]
+  fn rejects_an_empty_room() {
+    let robots = parse_robots("p=0,0 v=1,1");
+    let error = position_period(&robots, 0, 7).unwrap_err();
+    assert_eq!(error.to_string(), "room must be at least 1x1, not 0x7");
+    assert!(solve("p=0,0 v=1,1", 11, 0, SAFETY_SECONDS, 1).is_err());
+    assert!(solve("p=0,0 v=1,1", 11, -1, SAFETY_SECONDS, 2).is_err());
+  }
+
+  #[test]
+  fn finds_a_known_dense_frame() {
+    let robots = square_at(5, 11, 7);
+    assert_eq!(largest_cluster(&robots, 11, 7, 5), 9);
+    assert_eq!(find_egg_by_clustering(&robots, 11, 7, None).unwrap(), 5);
+    assert_eq!(find_egg_by_clustering(&robots, 11, 7, Some(9)).unwrap(), 5);
+    assert_eq!(
+      minimize_robot_time_to_display_easter_egg(&robots, 11, 7).unwrap(),
+      5
+    );
+  }
+
+  #[test]
+  fn picture_cluster_dwarfs_every_other_frame() {
+    let robots = parse_robots(include_str!("../input/day14_full.txt"));
+    let (picture, others): (Vec<_>, Vec<_>) = (0..position_period(&robots, 101, 103).unwrap())
+      .map(|seconds| (seconds, largest_cluster(&robots, 101, 103, seconds)))
+      .partition(|&(seconds, _)| seconds == 6668);
+    assert_eq!(picture, [(6668, 229)]);
+    assert_eq!(others.iter().map(|&(_, size)| size).max(), Some(14));
+    assert_eq!(
+      find_egg_by_clustering(&robots, 101, 103, Some(EGG_CLUSTER_SIZE)).unwrap(),
+      6668
+    );
+  }
+
+  #[test]
+  fn threshold_stops_at_the_first_big_enough_cluster() {
+    let robots = square_at(5, 11, 7);
+    let first_pair = (0..5)
+      .find(|&seconds| largest_cluster(&robots, 11, 7, seconds) >= 2)
+      .unwrap();
+    assert_eq!(
+      find_egg_by_clustering(&robots, 11, 7, Some(2)).unwrap(),
+      first_pair as usize
+    );
+  }
+}
@@ -1,9 +1,10 @@
 use anyhow::{Ok, Result};
+use aoc2024::grid::{Direction, Position};
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::fs;
+use std::{fmt, fs};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Cell {
+pub enum Cell {
   Wall,
   Box,
   BoxLeft,  // left part of wide box
@@ -35,56 +36,36 @@ impl Cell {
   }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Position {
-  row: i32,
-  col: i32,
+/// 100 times the distance from the top edge plus the distance from the left edge
+fn gps_coordinate(pos: Position) -> i32 {
+  100 * pos.row + pos.col
 }
 
-impl Position {
-  const fn new(row: i32, col: i32) -> Self {
-    Self { row, col }
-  }
-
-  fn move_in_direction(self, direction: Direction) -> Self {
-    match direction {
-      Direction::Up => Self::new(self.row - 1, self.col),
-      Direction::Down => Self::new(self.row + 1, self.col),
-      Direction::Left => Self::new(self.row, self.col - 1),
-      Direction::Right => Self::new(self.row, self.col + 1),
-    }
-  }
-
-  fn gps_coordinate(self) -> i32 {
-    100 * self.row + self.col
-  }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Direction {
-  Up,
-  Down,
-  Left,
-  Right,
-}
-
-impl Direction {
-  fn from_char(c: char) -> Option<Self> {
-    match c {
-      '^' => Some(Direction::Up),
-      'v' => Some(Direction::Down),
-      '<' => Some(Direction::Left),
-      '>' => Some(Direction::Right),
-      _ => None,
-    }
-  }
+/// Everything needed to revert one successful move
+#[derive(Debug)]
+struct UndoEntry {
+  robot_pos: Position,
+  cells: Vec<(Position, Option<Cell>)>, // prior contents, in the order they were overwritten
 }
 
-struct Warehouse {
+/// Warehouse map with the robot's position, driven one move at a time with `step`
+///
+/// ```
+/// use aoc2024::days::day15::Warehouse;
+/// use aoc2024::grid::{Direction, Position};
+///
+/// let mut warehouse = Warehouse::from_input("#####\n#.@O#\n#####\n\n<>");
+/// assert_eq!(warehouse.robot_position(), Position::new(1, 2));
+/// assert!(!warehouse.step(Direction::Right));
+/// assert_eq!(warehouse.calculate_gps_sum(), 103);
+/// ```
+pub struct Warehouse {
   grid: HashMap<Position, Cell>,
   robot_pos: Position,
   width: i32,
   height: i32,
+  journal: Option<Vec<(Position, Option<Cell>)>>, // cells overwritten by a `step` in progress
+  history: Vec<UndoEntry>,
 }
 
 impl Warehouse {
@@ -94,6 +75,8 @@ impl Warehouse {
       robot_pos,
       width,
       height,
+      journal: None,
+      history: Vec::new(),
     }
   }
 
@@ -170,22 +153,29 @@ impl Warehouse {
     Self::new(grid, robot_pos, width, height)
   }
 
-  fn from_input(input: &str) -> Self {
-    let (map_str, _) = input.split_once("\n\n").expect("Invalid input format");
-    Self::parse_map(map_str, false)
+  pub fn from_input(input: &str) -> Self {
+    let (map_str, _) = split_sections(input);
+    Self::parse_map(&map_str, false)
+  }
+
+  pub fn from_input_scaled(input: &str) -> Self {
+    let (map_str, _) = split_sections(input);
+    Self::parse_map(&map_str, true)
   }
 
-  fn from_input_scaled(input: &str) -> Self {
-    let (map_str, _) = input.split_once("\n\n").expect("Invalid input format");
-    Self::parse_map(map_str, true)
+  pub fn robot_position(&self) -> Position {
+    self.robot_pos
   }
 
-  fn get_cell(&self, pos: Position) -> Cell {
+  pub fn get_cell(&self, pos: Position) -> Cell {
     *self.grid.get(&pos).unwrap_or(&Cell::Wall)
   }
 
   fn set_cell(&mut self, pos: Position, cell: Cell) {
-    self.grid.insert(pos, cell);
+    let previous = self.grid.insert(pos, cell);
+    if let Some(journal) = &mut self.journal {
+      journal.push((pos, previous));
+    }
   }
 
   fn try_push_simple_boxes(
@@ -197,7 +187,7 @@ impl Warehouse {
     let mut current_pos = start_pos;
 
     loop {
-      current_pos = current_pos.move_in_direction(direction);
+      current_pos = current_pos.step(direction);
 
       match self.get_cell(current_pos) {
         Cell::Wall => return None,
@@ -220,16 +210,16 @@ impl Warehouse {
     match direction {
       Direction::Up | Direction::Down => {
         // for vertical movement, both parts of the box move
-        to_check.push_back(left_pos.move_in_direction(direction));
-        to_check.push_back(right_pos.move_in_direction(direction));
+        to_check.push_back(left_pos.step(direction));
+        to_check.push_back(right_pos.step(direction));
       }
       Direction::Left => {
         // for left movement, only check left of the left part
-        to_check.push_back(left_pos.move_in_direction(direction));
+        to_check.push_back(left_pos.step(direction));
       }
       Direction::Right => {
         // for right movement, only check right of the right part
-        to_check.push_back(right_pos.move_in_direction(direction));
+        to_check.push_back(right_pos.step(direction));
       }
     }
   }
@@ -242,7 +232,7 @@ impl Warehouse {
     let mut to_check = VecDeque::new();
     let mut boxes_to_move = HashSet::new();
 
-    to_check.push_back(start_pos.move_in_direction(direction));
+    to_check.push_back(start_pos.step(direction));
 
     while let Some(pos) = to_check.pop_front() {
       match self.get_cell(pos) {
@@ -264,7 +254,7 @@ impl Warehouse {
         }
         Cell::Box => {
           if boxes_to_move.insert(pos) {
-            to_check.push_back(pos.move_in_direction(direction));
+            to_check.push_back(pos.step(direction));
           }
         }
         Cell::Robot => panic!("Unexpected robot position."),
@@ -277,7 +267,7 @@ impl Warehouse {
   fn execute_simple_box_push(&mut self, box_positions: &[Position], direction: Direction) {
     // move all boxes one positionin the direction (in reverse order)
     for &box_pos in box_positions.iter().rev() {
-      let new_box_pos = box_pos.move_in_direction(direction);
+      let new_box_pos = box_pos.step(direction);
       self.set_cell(box_pos, Cell::Empty);
       self.set_cell(new_box_pos, Cell::Box);
     }
@@ -297,7 +287,7 @@ impl Warehouse {
 
     // pace boxes in their new positions
     for (pos, cell) in boxes_state {
-      let new_pos = pos.move_in_direction(direction);
+      let new_pos = pos.step(direction);
       self.set_cell(new_pos, cell);
     }
   }
@@ -308,42 +298,79 @@ impl Warehouse {
     self.robot_pos = new_pos;
   }
 
-  fn try_move_robot(&mut self, direction: Direction) {
-    let new_robot_pos = self.robot_pos.move_in_direction(direction);
+  fn try_move_robot(&mut self, direction: Direction) -> bool {
+    let new_robot_pos = self.robot_pos.step(direction);
 
     match self.get_cell(new_robot_pos) {
-      Cell::Wall => (), // can't move into wall
+      Cell::Wall => return false, // can't move into wall
       Cell::Empty => self.move_robot_to(new_robot_pos),
       Cell::Box => {
-        if let Some(box_pos) = self.try_push_simple_boxes(self.robot_pos, direction) {
-          self.execute_simple_box_push(&box_pos, direction);
-          self.move_robot_to(new_robot_pos);
-        }
+        let Some(box_pos) = self.try_push_simple_boxes(self.robot_pos, direction) else {
+          return false;
+        };
+        self.execute_simple_box_push(&box_pos, direction);
+        self.move_robot_to(new_robot_pos);
       }
       Cell::BoxLeft | Cell::BoxRight => {
-        if let Some(box_pos) = self.try_push_wide_boxes(self.robot_pos, direction) {
-          self.execute_wide_box_push(&box_pos, direction);
-          self.move_robot_to(new_robot_pos);
-        }
+        let Some(box_pos) = self.try_push_wide_boxes(self.robot_pos, direction) else {
+          return false;
+        };
+        self.execute_wide_box_push(&box_pos, direction);
+        self.move_robot_to(new_robot_pos);
       }
       Cell::Robot => panic!("Two robots found."),
     }
+
+    true
   }
 
-  fn execute_moves(&mut self, moves: &str) {
-    for ch in moves.chars() {
-      if let Some(dir) = Direction::from_char(ch) {
-        self.try_move_robot(dir);
-      }
+  /// Execute a single move, returning whether the robot moved. Successful moves can be
+  /// reverted with `undo`.
+  pub fn step(&mut self, direction: Direction) -> bool {
+    let robot_pos = self.robot_pos;
+    self.journal = Some(Vec::new());
+
+    let moved = self.try_move_robot(direction);
+    let cells = self.journal.take().unwrap_or_default();
+    if moved {
+      self.history.push(UndoEntry { robot_pos, cells });
     }
+    moved
   }
 
-  fn calculate_gps_sum(&self) -> i32 {
+  /// Revert the most recent successful move (robot and any pushed boxes).
+  /// Returns false when there is nothing left to undo.
+  pub fn undo(&mut self) -> bool {
+    let Some(entry) = self.history.pop() else {
+      return false;
+    };
+
+    for (pos, previous) in entry.cells.into_iter().rev() {
+      match previous {
+        Some(cell) => self.grid.insert(pos, cell),
+        None => self.grid.remove(&pos),
+      };
+    }
+    self.robot_pos = entry.robot_pos;
+    true
+  }
+
+  /// Run every move in `moves`, returning how many were blocked (by a wall or by boxes
+  /// that couldn't be pushed). Unlike `step`, these moves can't be undone.
+  pub fn execute_moves(&mut self, moves: &str) -> usize {
+    moves
+      .chars()
+      .filter_map(Direction::from_arrow)
+      .filter(|&dir| !self.try_move_robot(dir))
+      .count()
+  }
+
+  pub fn calculate_gps_sum(&self) -> i32 {
     self
       .grid
       .iter()
       .filter_map(|(pos, &cell)| match cell {
-        Cell::Box | Cell::BoxLeft => Some(pos.gps_coordinate()),
+        Cell::Box | Cell::BoxLeft => Some(gps_coordinate(*pos)),
         _ => None,
       })
       .sum()
@@ -351,23 +378,48 @@ impl Warehouse {
 
   #[allow(dead_code)]
   fn print_warehouse(&self) {
+    println!("{self}");
+  }
+}
+
+/// Grid rendering, one line per row; `to_string()` gives it as a value for snapshots
+impl fmt::Display for Warehouse {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     for row in 0..self.height {
       for col in 0..self.width {
         let pos = Position::new(row, col);
-        print!("{}", self.get_cell(pos).to_char());
+        write!(f, "{}", self.get_cell(pos).to_char())?;
       }
-      println!();
+      writeln!(f)?;
     }
-    println!();
+    fmt::Result::Ok(())
   }
 }
 
-fn parse_moves(input: &str) -> String {
-  let (_, moves_str) = input.split_once("\n\n").expect("Invalid input format");
-  moves_str.replace('\n', "")
+/// Split the input into (map, moves) at the first blank line. Tolerates CRLF line endings
+/// and trailing whitespace; the moves come back with all line breaks removed.
+fn split_sections(input: &str) -> (String, String) {
+  // `lines` already drops the '\r' of CRLF endings
+  let mut lines = input.lines().map(str::trim_end);
+  let map_str = lines
+    .by_ref()
+    .skip_while(|line| line.is_empty())
+    .take_while(|line| !line.is_empty())
+    .collect::<Vec<_>>()
+    .join("\n");
+  let moves_str = lines
+    .flat_map(str::chars)
+    .filter(|c| !c.is_whitespace())
+    .collect();
+  (map_str, moves_str)
 }
 
-fn solve(input: &str, part: u8) -> i32 {
+pub fn parse_moves(input: &str) -> String {
+  let (_, moves_str) = split_sections(input);
+  moves_str
+}
+
+pub(crate) fn solve(input: &str, part: u8) -> i32 {
   let mut warehouse = match part {
     1 => Warehouse::from_input(input),
     2 => Warehouse::from_input_scaled(input),
@@ -379,7 +431,7 @@ fn solve(input: &str, part: u8) -> i32 {
   warehouse.calculate_gps_sum()
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
   println!("Part 1 result = {}", solve(&input, 1));
@@ -387,8 +439,93 @@ fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day15_simple.txt", "Simple puzzle")?;
   print_result("input/day15_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day15_simple.txt");
+
+  #[test]
+  fn sample_answers() {
+    assert_eq!(solve(SAMPLE, 1), 10092);
+    assert_eq!(solve(SAMPLE, 2), 9021);
+  }
+
+  #[test]
+  fn crlf_input_matches_lf() {
+    let crlf = SAMPLE.replace('\n', "  \r\n");
+    assert!(!parse_moves(&crlf).contains('\r'));
+    assert_eq!(parse_moves(&crlf), parse_moves(SAMPLE));
+    assert_eq!(solve(&crlf, 1), solve(SAMPLE, 1));
+    assert_eq!(solve(&crlf, 2), solve(SAMPLE, 2));
+  }
+
+  #[test]
+  fn counts_blocked_moves() {
+    // Left into the wall, then right into a box that is against the wall
+    let input = "#####\n#.@O#\n#####\n\n<<>>";
+    let mut warehouse = Warehouse::from_input(input);
+    assert_eq!(warehouse.execute_moves(&parse_moves(input)), 2);
+    assert_eq!(warehouse.robot_position(), Position::new(1, 2));
+  }
+
+  #[test]
+  fn undo_reverts_steps_and_pushed_boxes() {
+    for mut warehouse in [
+      Warehouse::from_input(SAMPLE),
+      Warehouse::from_input_scaled(SAMPLE),
+    ] {
+      let initial = warehouse.to_string();
+      let initial_gps = warehouse.calculate_gps_sum();
+      let start = warehouse.robot_position();
+
+      let moves: Vec<Direction> = parse_moves(SAMPLE)
+        .chars()
+        .take(40)
+        .filter_map(Direction::from_arrow)
+        .collect();
+      let moved = moves.iter().filter(|&&dir| warehouse.step(dir)).count();
+      assert!(moved > 0);
+      assert_ne!(warehouse.calculate_gps_sum(), initial_gps);
+
+      for _ in 0..moved {
+        assert!(warehouse.undo());
+      }
+      assert!(!warehouse.undo());
+      assert_eq!(warehouse.calculate_gps_sum(), initial_gps);
+      assert_eq!(warehouse.robot_position(), start);
+      assert_eq!(warehouse.to_string(), initial);
+    }
+  }
+
+  #[test]
+  fn renders_intermediate_states() {
+    let input = "#######\n#.@O..#\n#######\n\n>>";
+    let mut warehouse = Warehouse::from_input(input);
+    assert_eq!(warehouse.to_string(), "#######\n#.@O..#\n#######\n");
+    warehouse.step(Direction::Right);
+    warehouse.step(Direction::Right);
+    assert_eq!(warehouse.to_string(), "#######\n#...@O#\n#######\n");
+
+    let mut wide = Warehouse::from_input_scaled(input);
+    wide.step(Direction::Right);
+    assert_eq!(
+      wide.to_string(),
+      "##############\n##...@[]....##\n##############\n"
+    );
+  }
+
+  #[test]
+  fn execute_moves_keeps_no_undo_history() {
+    let mut warehouse = Warehouse::from_input(SAMPLE);
+    warehouse.execute_moves(&parse_moves(SAMPLE));
+    assert!(warehouse.history.is_empty());
+    assert!(!warehouse.undo());
+  }
+}
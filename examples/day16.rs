@@ -1,76 +1,17 @@
 use anyhow::Result;
+use aoc2024::grid::{Direction, Grid, Position};
+use std::cell::OnceCell;
 use std::collections::{BinaryHeap, HashMap};
 use std::fs;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Direction {
-  North,
-  East,
-  South,
-  West,
-}
-
-impl Direction {
-  fn turn_clockwise(self) -> Self {
-    match self {
-      Direction::North => Direction::East,
-      Direction::East => Direction::South,
-      Direction::South => Direction::West,
-      Direction::West => Direction::North,
-    }
-  }
-
-  fn turn_counterclockwise(self) -> Self {
-    match self {
-      Direction::North => Direction::West,
-      Direction::West => Direction::South,
-      Direction::South => Direction::East,
-      Direction::East => Direction::North,
-    }
-  }
-
-  fn delta(self) -> (i32, i32) {
-    match self {
-      Direction::North => (-1, 0),
-      Direction::East => (0, 1),
-      Direction::South => (1, 0),
-      Direction::West => (0, -1),
-    }
-  }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Position {
-  row: usize,
-  col: usize,
-}
-
-impl Position {
-  fn new(row: usize, col: usize) -> Self {
-    Self { row, col }
-  }
-
-  fn move_in_direction(self, direction: Direction, rows: usize, cols: usize) -> Option<Self> {
-    let (dr, dc) = direction.delta();
-    let new_row = self.row as i32 + dr;
-    let new_col = self.col as i32 + dc;
-
-    if new_row >= 0 && new_row < rows as i32 && new_col >= 0 && new_col < cols as i32 {
-      Some(Position::new(new_row as usize, new_col as usize))
-    } else {
-      None
-    }
-  }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct State {
-  pos: Position,
-  dir: Direction,
+pub struct State {
+  pub pos: Position,
+  pub dir: Direction,
 }
 
 impl State {
-  fn new(pos: Position, dir: Direction) -> Self {
+  pub fn new(pos: Position, dir: Direction) -> Self {
     Self { pos, dir }
   }
 }
@@ -93,52 +34,94 @@ impl PartialOrd for Node {
   }
 }
 
-struct Maze {
-  grid: Vec<Vec<char>>,
+/// Score charged per forward step and per 90° turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Costs {
+  pub step: u32,
+  pub turn: u32,
+}
+
+impl Default for Costs {
+  fn default() -> Self {
+    Self {
+      step: 1,
+      turn: 1000,
+    }
+  }
+}
+
+/// Reindeer maze; the searches from the start and from the ends each run once and are
+/// reused by every query
+///
+/// ```
+/// use aoc2024::days::day16::{Maze, State};
+/// use aoc2024::grid::{Direction, Position};
+///
+/// let maze = Maze::from_input("####\n#SE#\n####");
+/// let from_start = maze.dijkstra_from_start();
+/// assert_eq!(from_start[&State::new(Position::new(1, 1), Direction::Right)], 0);
+/// assert_eq!(maze.best_cost_at(Position::new(1, 2)), Some(1));
+/// ```
+pub struct Maze {
+  grid: Grid<char>,
   start_pos: Position,
-  end_pos: Position,
-  rows: usize,
-  cols: usize,
+  end_positions: Vec<Position>, // every 'E' tile; reaching any of them finishes
+  costs: Costs,
+  from_start: OnceCell<(HashMap<State, u32>, HashMap<State, State>)>,
+  from_end: OnceCell<HashMap<State, u32>>,
 }
 
 impl Maze {
-  fn from_input(input: &str) -> Self {
-    let lines: Vec<&str> = input.trim().lines().collect();
-    let rows = lines.len();
-    let cols = lines[0].len();
-    let mut grid = vec![vec!['.'; cols]; rows];
-    let mut start_pos = Position::new(0, 0);
-    let mut end_pos = Position::new(0, 0);
-
-    for (row, line) in lines.iter().enumerate() {
-      for (col, ch) in line.chars().enumerate() {
-        grid[row][col] = ch;
-        match ch {
-          'S' => start_pos = Position::new(row, col),
-          'E' => end_pos = Position::new(row, col),
-          _ => {}
-        }
-      }
-    }
+  pub fn from_input(input: &str) -> Self {
+    let grid = Grid::parse(input.trim());
+    let start_pos = grid.find(|&ch| ch == 'S').unwrap_or(Position::new(0, 0));
+    let end_positions = grid
+      .iter()
+      .filter(|&(_, &ch)| ch == 'E')
+      .map(|(pos, _)| pos)
+      .collect();
 
     Self {
       grid,
       start_pos,
-      end_pos,
-      rows,
-      cols,
+      end_positions,
+      costs: Costs::default(),
+      from_start: OnceCell::new(),
+      from_end: OnceCell::new(),
     }
   }
 
+  /// Score the maze with different step/turn costs
+  pub fn with_costs(mut self, costs: Costs) -> Self {
+    self.costs = costs;
+    self.from_start = OnceCell::new();
+    self.from_end = OnceCell::new();
+    self
+  }
+
+  /// Off the grid counts as wall
   fn is_wall(&self, pos: Position) -> bool {
-    self.grid[pos.row][pos.col] == '#'
+    self.grid.get(pos).is_none_or(|&ch| ch == '#')
   }
 
-  fn dijkstra_from_start(&self) -> HashMap<State, u32> {
+  /// Cheapest score to reach every reachable state from the start (facing east)
+  pub fn dijkstra_from_start(&self) -> &HashMap<State, u32> {
+    &self.dijkstra_from_start_with_predecessors().0
+  }
+
+  /// Distances from the start plus, for every reached state, the state it was last relaxed
+  /// from; following predecessors back from any state traces one cheapest route to it.
+  fn dijkstra_from_start_with_predecessors(&self) -> &(HashMap<State, u32>, HashMap<State, State>) {
+    self.from_start.get_or_init(|| self.search_from_start())
+  }
+
+  /// Uncached search behind `dijkstra_from_start_with_predecessors`
+  fn search_from_start(&self) -> (HashMap<State, u32>, HashMap<State, State>) {
     let mut heap = BinaryHeap::new();
     let mut distances: HashMap<State, u32> = HashMap::new();
+    let mut predecessors: HashMap<State, State> = HashMap::new();
 
-    let start_state = State::new(self.start_pos, Direction::East);
+    let start_state = State::new(self.start_pos, Direction::Right);
     heap.push(Node {
       cost: 0,
       state: start_state,
@@ -146,35 +129,35 @@ impl Maze {
     distances.insert(start_state, 0);
 
     while let Some(Node { cost, state }) = heap.pop() {
-      if let Some(&best_cost) = distances.get(&state) {
-        if cost > best_cost {
-          continue;
-        }
+      if let Some(&best_cost) = distances.get(&state)
+        && cost > best_cost
+      {
+        continue;
       }
 
-      // Try moving forward (cost: 1)
-      if let Some(next_pos) = state.pos.move_in_direction(state.dir, self.rows, self.cols) {
-        if !self.is_wall(next_pos) {
-          let next_state = State::new(next_pos, state.dir);
-          let next_cost = cost + 1;
-
-          let should_update = distances
-            .get(&next_state)
-            .is_none_or(|&existing_cost| next_cost < existing_cost);
-
-          if should_update {
-            distances.insert(next_state, next_cost);
-            heap.push(Node {
-              cost: next_cost,
-              state: next_state,
-            });
-          }
+      // Try moving forward
+      let next_pos = state.pos.step(state.dir);
+      if !self.is_wall(next_pos) {
+        let next_state = State::new(next_pos, state.dir);
+        let next_cost = cost + self.costs.step;
+
+        let should_update = distances
+          .get(&next_state)
+          .is_none_or(|&existing_cost| next_cost < existing_cost);
+
+        if should_update {
+          distances.insert(next_state, next_cost);
+          predecessors.insert(next_state, state);
+          heap.push(Node {
+            cost: next_cost,
+            state: next_state,
+          });
         }
       }
 
-      // Try turning clockwise (cost: 1000)
+      // Try turning clockwise
       let clockwise_state = State::new(state.pos, state.dir.turn_clockwise());
-      let turn_cost = cost + 1000;
+      let turn_cost = cost + self.costs.turn;
 
       let should_update = distances
         .get(&clockwise_state)
@@ -182,13 +165,14 @@ impl Maze {
 
       if should_update {
         distances.insert(clockwise_state, turn_cost);
+        predecessors.insert(clockwise_state, state);
         heap.push(Node {
           cost: turn_cost,
           state: clockwise_state,
         });
       }
 
-      // Try turning counterclockwise (cost: 1000)
+      // Try turning counterclockwise
       let counterclockwise_state = State::new(state.pos, state.dir.turn_counterclockwise());
 
       let should_update = distances
@@ -197,6 +181,7 @@ impl Maze {
 
       if should_update {
         distances.insert(counterclockwise_state, turn_cost);
+        predecessors.insert(counterclockwise_state, state);
         heap.push(Node {
           cost: turn_cost,
           state: counterclockwise_state,
@@ -204,68 +189,60 @@ impl Maze {
       }
     }
 
-    distances
+    (distances, predecessors)
   }
 
-  fn dijkstra_from_end(&self) -> HashMap<State, u32> {
+  /// Cheapest score from every state to the nearest end (arriving in any direction)
+  pub fn dijkstra_from_end(&self) -> &HashMap<State, u32> {
+    self.from_end.get_or_init(|| self.search_from_end())
+  }
+
+  /// Uncached search behind `dijkstra_from_end`
+  fn search_from_end(&self) -> HashMap<State, u32> {
     let mut heap = BinaryHeap::new();
     let mut distances: HashMap<State, u32> = HashMap::new();
 
-    // Start from end position in all directions
-    for &dir in &[
-      Direction::North,
-      Direction::East,
-      Direction::South,
-      Direction::West,
-    ] {
-      let end_state = State::new(self.end_pos, dir);
-      heap.push(Node {
-        cost: 0,
-        state: end_state,
-      });
-      distances.insert(end_state, 0);
+    // Start from every end position in all directions
+    for &end_pos in &self.end_positions {
+      for dir in Direction::ALL {
+        let end_state = State::new(end_pos, dir);
+        heap.push(Node {
+          cost: 0,
+          state: end_state,
+        });
+        distances.insert(end_state, 0);
+      }
     }
 
     while let Some(Node { cost, state }) = heap.pop() {
-      if let Some(&best_cost) = distances.get(&state) {
-        if cost > best_cost {
-          continue;
-        }
+      if let Some(&best_cost) = distances.get(&state)
+        && cost > best_cost
+      {
+        continue;
       }
 
       // Try moving backward (reverse direction)
-      let reverse_dir = match state.dir {
-        Direction::North => Direction::South,
-        Direction::East => Direction::West,
-        Direction::South => Direction::North,
-        Direction::West => Direction::East,
-      };
-
-      if let Some(prev_pos) = state
-        .pos
-        .move_in_direction(reverse_dir, self.rows, self.cols)
-      {
-        if !self.is_wall(prev_pos) {
-          let prev_state = State::new(prev_pos, state.dir);
-          let prev_cost = cost + 1;
-
-          let should_update = distances
-            .get(&prev_state)
-            .is_none_or(|&existing_cost| prev_cost < existing_cost);
-
-          if should_update {
-            distances.insert(prev_state, prev_cost);
-            heap.push(Node {
-              cost: prev_cost,
-              state: prev_state,
-            });
-          }
+      let prev_pos = state.pos.step(state.dir.opposite());
+      if !self.is_wall(prev_pos) {
+        let prev_state = State::new(prev_pos, state.dir);
+        let prev_cost = cost + self.costs.step;
+
+        let should_update = distances
+          .get(&prev_state)
+          .is_none_or(|&existing_cost| prev_cost < existing_cost);
+
+        if should_update {
+          distances.insert(prev_state, prev_cost);
+          heap.push(Node {
+            cost: prev_cost,
+            state: prev_state,
+          });
         }
       }
 
       // Try reverse turns (clockwise -> counterclockwise, counterclockwise -> clockwise)
       let from_clockwise = State::new(state.pos, state.dir.turn_counterclockwise());
-      let turn_cost = cost + 1000;
+      let turn_cost = cost + self.costs.turn;
 
       let should_update = distances
         .get(&from_clockwise)
@@ -297,21 +274,154 @@ impl Maze {
     distances
   }
 
+  /// Cheapest score to stand on `pos` facing any direction, or `None` if unreachable
+  pub fn best_cost_at(&self, pos: Position) -> Option<u32> {
+    let distances = self.dijkstra_from_start();
+    Direction::ALL
+      .into_iter()
+      .filter_map(|dir| distances.get(&State::new(pos, dir)).copied())
+      .min()
+  }
+
+  /// Cheapest score to reach any end
   fn find_minimum_score(&self) -> u32 {
     let distances = self.dijkstra_from_start();
 
-    // Find minimum cost to reach end position from any direction
-    [
-      Direction::North,
-      Direction::East,
-      Direction::South,
-      Direction::West,
-    ]
-    .iter()
-    .filter_map(|&dir| distances.get(&State::new(self.end_pos, dir)))
-    .min()
-    .copied()
-    .unwrap_or(u32::MAX)
+    // Find minimum cost to reach any end position from any direction
+    self
+      .end_states()
+      .filter_map(|state| distances.get(&state))
+      .min()
+      .copied()
+      .unwrap_or(u32::MAX)
+  }
+
+  /// Every end position paired with every facing direction
+  fn end_states(&self) -> impl Iterator<Item = State> + '_ {
+    self
+      .end_positions
+      .iter()
+      .flat_map(|&pos| Direction::ALL.map(|dir| State::new(pos, dir)))
+  }
+
+  /// Admissible (and consistent) lower bound on the cost from `state` to the nearest end
+  fn heuristic(&self, state: State) -> u32 {
+    self
+      .end_positions
+      .iter()
+      .map(|&end_pos| self.heuristic_to(state, end_pos))
+      .min()
+      .unwrap_or(0)
+  }
+
+  /// Manhattan distance in steps plus the fewest turns needed to face every required direction
+  fn heuristic_to(&self, state: State, end_pos: Position) -> u32 {
+    let d_row = end_pos.row - state.pos.row;
+    let d_col = end_pos.col - state.pos.col;
+    let distance = state.pos.manhattan_distance(end_pos);
+
+    let vertical = match d_row.signum() {
+      -1 => Some(Direction::Up),
+      1 => Some(Direction::Down),
+      _ => None,
+    };
+    let horizontal = match d_col.signum() {
+      -1 => Some(Direction::Left),
+      1 => Some(Direction::Right),
+      _ => None,
+    };
+    let needed: Vec<Direction> = [vertical, horizontal].into_iter().flatten().collect();
+
+    let turns = match needed.as_slice() {
+      [] => 0,
+      [only] if *only == state.dir => 0,
+      // Perpendicular needs one turn, facing away needs two
+      [only] if only.opposite() == state.dir => 2,
+      [_] => 1,
+      both if both.contains(&state.dir) => 1,
+      _ => 2,
+    };
+
+    distance * self.costs.step + turns * self.costs.turn
+  }
+
+  /// Same score as `find_minimum_score`, but guided towards the end by `heuristic`
+  /// so it can stop without exploring the whole maze
+  pub fn find_minimum_score_astar(&self) -> u32 {
+    let mut heap = BinaryHeap::new();
+    let mut distances: HashMap<State, u32> = HashMap::new();
+
+    let start_state = State::new(self.start_pos, Direction::Right);
+    heap.push(Node {
+      cost: self.heuristic(start_state),
+      state: start_state,
+    });
+    distances.insert(start_state, 0);
+
+    // Node::cost holds the estimate g + h; g is looked up in `distances`
+    while let Some(Node { cost, state }) = heap.pop() {
+      let g = distances[&state];
+      if cost > g + self.heuristic(state) {
+        continue;
+      }
+      if self.end_positions.contains(&state.pos) {
+        return g;
+      }
+
+      let mut neighbors = vec![
+        (
+          State::new(state.pos, state.dir.turn_clockwise()),
+          self.costs.turn,
+        ),
+        (
+          State::new(state.pos, state.dir.turn_counterclockwise()),
+          self.costs.turn,
+        ),
+      ];
+      let next_pos = state.pos.step(state.dir);
+      if !self.is_wall(next_pos) {
+        neighbors.push((State::new(next_pos, state.dir), self.costs.step));
+      }
+
+      for (next_state, move_cost) in neighbors {
+        let next_g = g + move_cost;
+        if distances
+          .get(&next_state)
+          .is_none_or(|&existing| next_g < existing)
+        {
+          distances.insert(next_state, next_g);
+          heap.push(Node {
+            cost: next_g + self.heuristic(next_state),
+            state: next_state,
+          });
+        }
+      }
+    }
+
+    u32::MAX
+  }
+
+  /// One minimum-cost sequence of states from the start (facing east) to the cheapest end,
+  /// where consecutive states differ by a single step or a single 90° turn.
+  /// Empty if the end is unreachable.
+  pub fn optimal_path(&self) -> Vec<State> {
+    let (distances, predecessors) = self.dijkstra_from_start_with_predecessors();
+
+    let Some(mut state) = self
+      .end_states()
+      .filter(|state| distances.contains_key(state))
+      .min_by_key(|state| distances[state])
+    else {
+      return Vec::new();
+    };
+
+    let mut path = vec![state];
+    while let Some(&prev) = predecessors.get(&state) {
+      path.push(prev);
+      state = prev;
+    }
+    path.reverse();
+    path
   }
 
   fn find_optimal_tiles(&self) -> usize {
@@ -323,29 +433,20 @@ impl Maze {
 
     // A tile is optimal if there exists a direction such that:
     // distance_from_start(pos, dir) + distance_to_end(pos, dir) == min_score
-    for row in 0..self.rows {
-      for col in 0..self.cols {
-        let pos = Position::new(row, col);
-        if self.is_wall(pos) {
-          continue;
-        }
+    for pos in self.grid.positions() {
+      if self.is_wall(pos) {
+        continue;
+      }
+
+      for dir in Direction::ALL {
+        let state = State::new(pos, dir);
 
-        for &dir in &[
-          Direction::North,
-          Direction::East,
-          Direction::South,
-          Direction::West,
-        ] {
-          let state = State::new(pos, dir);
-
-          if let (Some(&dist_from_start), Some(&dist_to_end)) =
-            (from_start.get(&state), from_end.get(&state))
-          {
-            if dist_from_start + dist_to_end == min_score {
-              optimal_tiles.insert(pos);
-              break; // Found one direction that works, no need to check others
-            }
-          }
+        if let (Some(&dist_from_start), Some(&dist_to_end)) =
+          (from_start.get(&state), from_end.get(&state))
+          && dist_from_start + dist_to_end == min_score
+        {
+          optimal_tiles.insert(pos);
+          break; // Found one direction that works, no need to check others
         }
       }
     }
@@ -354,7 +455,7 @@ impl Maze {
   }
 }
 
-fn solve(input: &str, part: u8) -> usize {
+pub(crate) fn solve(input: &str, part: u8) -> usize {
   let maze = Maze::from_input(input);
   match part {
     1 => maze.find_minimum_score() as usize,
@@ -363,16 +464,171 @@ fn solve(input: &str, part: u8) -> usize {
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
   println!("Part 1 result = {}", solve(&input, 1));
-  println!("Part 2 result = {}\n", solve(&input, 2));
+  println!("Part 2 result = {}", solve(&input, 2));
+  let path = Maze::from_input(&input).optimal_path();
+  println!("Optimal path length = {} states\n", path.len());
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day16_simple.txt", "Simple puzzle")?;
   print_result("input/day16_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day16_simple.txt");
+
+  /// Cost of a single transition, or `None` if it's neither one step nor one turn
+  fn transition_cost(maze: &Maze, from: State, to: State) -> Option<u32> {
+    if to.pos == from.pos
+      && (to.dir == from.dir.turn_clockwise() || to.dir == from.dir.turn_counterclockwise())
+    {
+      Some(maze.costs.turn)
+    } else if to.dir == from.dir && to.pos == from.pos.step(from.dir) && !maze.is_wall(to.pos) {
+      Some(maze.costs.step)
+    } else {
+      None
+    }
+  }
+
+  #[test]
+  fn sample_answers() {
+    assert_eq!(solve(SAMPLE, 1), 7036);
+    assert_eq!(solve(SAMPLE, 2), 45);
+  }
+
+  #[test]
+  fn default_costs() {
+    assert_eq!(
+      Costs::default(),
+      Costs {
+        step: 1,
+        turn: 1000
+      }
+    );
+  }
+
+  #[test]
+  fn cheaper_turns_change_the_best_route() {
+    // Two turns along the outside (12 steps) or four through the zigzag (8 steps)
+    let input = "\
+#########
+#S......#
+#.#####.#
+#...###.#
+###.###.#
+###..E..#
+#########";
+    assert_eq!(Maze::from_input(input).find_minimum_score(), 2012);
+
+    let cheap_turns = Maze::from_input(input).with_costs(Costs { step: 1, turn: 1 });
+    assert_eq!(cheap_turns.find_minimum_score(), 12);
+    assert_eq!(cheap_turns.optimal_path().len(), 13);
+
+    let slow_steps = Maze::from_input(input).with_costs(Costs {
+      step: 1000,
+      turn: 1,
+    });
+    assert_eq!(slow_steps.find_minimum_score(), 8004);
+  }
+
+  #[test]
+  fn astar_matches_dijkstra() {
+    let second_sample = "\
+#################
+#...#...#...#..E#
+#.#.#.#.#.#.#.#.#
+#.#.#.#...#...#.#
+#.#.#.#.###.#.#.#
+#...#.#.#.....#.#
+#.#.#.#.#.#####.#
+#.#...#.#.#.....#
+#.#.#####.#.###.#
+#.#.#.......#...#
+#.#.###.#####.###
+#.#.#...#.....#.#
+#.#.#.#####.###.#
+#.#.#.........#.#
+#.#.#.#########.#
+#S#.............#
+#################";
+    let maze = Maze::from_input(second_sample);
+    assert_eq!(maze.find_minimum_score(), 11048);
+    assert_eq!(maze.find_minimum_score_astar(), 11048);
+
+    let maze = Maze::from_input(SAMPLE);
+    assert_eq!(maze.find_minimum_score_astar(), maze.find_minimum_score());
+
+    let maze = maze.with_costs(Costs { step: 7, turn: 3 });
+    assert_eq!(maze.find_minimum_score_astar(), maze.find_minimum_score());
+  }
+
+  #[test]
+  fn distance_maps_are_computed_once() {
+    let maze = Maze::from_input(SAMPLE);
+    assert!(std::ptr::eq(
+      maze.dijkstra_from_start(),
+      maze.dijkstra_from_start()
+    ));
+    assert!(std::ptr::eq(
+      maze.dijkstra_from_end(),
+      maze.dijkstra_from_end()
+    ));
+
+    let start = maze.start_pos;
+    assert_eq!(maze.best_cost_at(start), Some(0));
+    let end = maze.end_positions[0];
+    assert_eq!(maze.best_cost_at(end), Some(7036));
+    assert_eq!(maze.best_cost_at(Position::new(0, 0)), None);
+
+    // Swapping the costs drops the cached maps
+    let maze = maze.with_costs(Costs { step: 1, turn: 1 });
+    assert!(maze.best_cost_at(end).unwrap() < 7036);
+  }
+
+  #[test]
+  fn nearest_of_several_ends_wins() {
+    let maze = Maze::from_input("########\n#E..S.E#\n########");
+    assert_eq!(maze.end_positions.len(), 2);
+    assert_eq!(maze.find_minimum_score(), 2);
+    assert_eq!(maze.find_optimal_tiles(), 3);
+    assert_eq!(maze.optimal_path().last().unwrap().pos, Position::new(1, 6));
+  }
+
+  #[test]
+  fn equally_near_ends_union_their_tiles() {
+    let maze = Maze::from_input("###\n#E#\n#.#\n#S#\n#.#\n#E#\n###");
+    assert_eq!(maze.find_minimum_score(), 1002);
+    assert_eq!(maze.find_optimal_tiles(), 5);
+  }
+
+  #[test]
+  fn optimal_path_is_legal_and_cheapest() {
+    let maze = Maze::from_input(SAMPLE);
+    let path = maze.optimal_path();
+
+    assert_eq!(path[0], State::new(maze.start_pos, Direction::Right));
+    assert!(maze.end_positions.contains(&path[path.len() - 1].pos));
+
+    let cost: u32 = path
+      .windows(2)
+      .map(|pair| transition_cost(&maze, pair[0], pair[1]).expect("illegal transition"))
+      .sum();
+    assert_eq!(cost, maze.find_minimum_score());
+  }
+
+  #[test]
+  fn optimal_path_is_empty_when_the_end_is_unreachable() {
+    let maze = Maze::from_input("#####\n#S#E#\n#####");
+    assert!(maze.optimal_path().is_empty());
+    assert_eq!(maze.find_minimum_score(), u32::MAX);
+  }
+}
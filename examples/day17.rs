@@ -3,10 +3,10 @@ use std::{collections::HashSet, fs};
 
 /// CPU registers
 #[derive(Clone, Copy, Debug)]
-struct Regs {
-  a: i128,
-  b: i128,
-  c: i128,
+pub struct Regs {
+  pub a: i128,
+  pub b: i128,
+  pub c: i128,
 }
 
 impl Regs {
@@ -24,7 +24,7 @@ impl Regs {
 }
 
 /// Parse the block that AoC gives us.
-fn parse_input(txt: &str) -> Result<(Regs, Vec<u8>)> {
+pub fn parse_input(txt: &str) -> Result<(Regs, Vec<u8>)> {
   let mut a = None;
   let mut b = None;
   let mut c = None;
@@ -56,16 +56,55 @@ fn parse_input(txt: &str) -> Result<(Regs, Vec<u8>)> {
   ))
 }
 
-/// Run the full program and return everything the `out` instruction emits.
-fn exec(mut regs: Regs, prog: &[u8]) -> Result<Vec<u8>> {
-  let mut pc = 0usize;
-  let mut out = Vec::new();
+/// Single-stepping interpreter: register file, program counter and output so far.
+///
+/// ```
+/// use aoc2024::days::day17::{Cpu, Regs};
+///
+/// // out A, out B, out C
+/// let mut cpu = Cpu::new(Regs { a: 10, b: 3, c: 12 }, &[5, 4, 5, 5, 5, 6]);
+/// let mut out = Vec::new();
+/// while !cpu.halted() {
+///   out.extend(cpu.step().unwrap());
+/// }
+/// assert_eq!(out, [2, 3, 4]);
+/// ```
+pub struct Cpu<'a> {
+  regs: Regs,
+  pc: usize,
+  out: Vec<u8>,
+  prog: &'a [u8],
+}
 
-  while pc < prog.len() {
-    let opcode = prog[pc];
-    let operand = *prog
-      .get(pc + 1)
+impl<'a> Cpu<'a> {
+  pub fn new(regs: Regs, prog: &'a [u8]) -> Self {
+    Self {
+      regs,
+      pc: 0,
+      out: Vec::new(),
+      prog,
+    }
+  }
+
+  /// The program halts once the pc runs past the last instruction.
+  pub fn halted(&self) -> bool {
+    self.pc >= self.prog.len()
+  }
+
+  /// Execute one instruction; returns the digit it emitted, if any.
+  /// Stepping a halted CPU is a no-op.
+  pub fn step(&mut self) -> Result<Option<u8>> {
+    if self.halted() {
+      return Ok(None);
+    }
+
+    let regs = &mut self.regs;
+    let opcode = self.prog[self.pc];
+    let operand = *self
+      .prog
+      .get(self.pc + 1)
       .context("dangling opcode at end of program")?;
+    let mut emitted = None;
 
     match opcode {
       0 | 6 | 7 => {
@@ -88,18 +127,98 @@ fn exec(mut regs: Regs, prog: &[u8]) -> Result<Vec<u8>> {
       2 => regs.b = regs.combo(operand) & 7,
       3 => {
         if regs.a != 0 {
-          pc = operand as usize;
-          continue;
+          self.pc = operand as usize;
+          return Ok(None);
         }
       }
       4 => regs.b ^= regs.c,
-      5 => out.push((regs.combo(operand) & 7) as u8),
+      5 => emitted = Some((regs.combo(operand) & 7) as u8),
       _ => bail!("unknown opcode {opcode}"),
     }
 
-    pc += 2;
+    self.out.extend(emitted);
+    self.pc += 2;
+    Ok(emitted)
   }
-  Ok(out)
+}
+
+/// Default cap on executed instructions before `exec` gives up on a program.
+const INSTRUCTION_BUDGET: usize = 10_000_000;
+
+/// Run the full program and return everything the `out` instruction emits.
+/// Fails if the program hasn't halted after `budget` instructions (e.g. `jnz` to itself).
+pub fn exec(regs: Regs, prog: &[u8], budget: usize) -> Result<Vec<u8>> {
+  let mut cpu = Cpu::new(regs, prog);
+  for _ in 0..budget {
+    if cpu.halted() {
+      return Ok(cpu.out);
+    }
+    cpu.step()?;
+  }
+  if !cpu.halted() {
+    bail!(
+      "program did not halt within {budget} instructions (pc = {})",
+      cpu.pc
+    );
+  }
+  Ok(cpu.out)
+}
+
+/// Like `exec`, but records every executed instruction as
+/// `(pc, registers after it ran, emitted digit)`.
+pub fn exec_trace(regs: Regs, prog: &[u8]) -> Result<Vec<(usize, Regs, Option<u8>)>> {
+  let mut cpu = Cpu::new(regs, prog);
+  let mut trace = Vec::new();
+  while !cpu.halted() {
+    let pc = cpu.pc;
+    let emitted = cpu.step()?;
+    trace.push((pc, cpu.regs, emitted));
+  }
+  Ok(trace)
+}
+
+/// Render a *combo* operand the way it is evaluated: literals 0‑3 or a register name.
+fn combo_name(op: u8) -> String {
+  match op {
+    0..=3 => op.to_string(),
+    4 => "A".into(),
+    5 => "B".into(),
+    6 => "C".into(),
+    _ => format!("<reserved {op}>"),
+  }
+}
+
+/// One line per instruction: `pc: mnemonic operand ; effect`.
+pub fn disassemble(prog: &[u8]) -> Vec<String> {
+  prog
+    .chunks(2)
+    .enumerate()
+    .map(|(i, instr)| {
+      let pc = i * 2;
+      let opcode = instr[0];
+      let Some(&operand) = instr.get(1) else {
+        return format!("{pc}: <dangling opcode {opcode}>");
+      };
+      let combo = combo_name(operand);
+
+      let (mnemonic, arg, effect) = match opcode {
+        0 => ("adv", combo.clone(), format!("A = A >> {combo}")),
+        1 => ("bxl", operand.to_string(), format!("B = B ^ {operand}")),
+        2 => ("bst", combo.clone(), format!("B = {combo} % 8")),
+        3 => (
+          "jnz",
+          operand.to_string(),
+          format!("if A != 0 jump to {operand}"),
+        ),
+        4 => ("bxc", operand.to_string(), "B = B ^ C".into()),
+        5 => ("out", combo.clone(), format!("output {combo} % 8")),
+        6 => ("bdv", combo.clone(), format!("B = A >> {combo}")),
+        7 => ("cdv", combo.clone(), format!("C = A >> {combo}")),
+        _ => return format!("{pc}: <unknown opcode {opcode}> {operand}"),
+      };
+      format!("{pc}: {mnemonic} {arg} ; {effect}")
+    })
+    .collect()
 }
 
 /**
@@ -183,21 +302,74 @@ fn step_once(a0: i128, init_b: i128, init_c: i128, prog: &[u8]) -> Result<(u8, i
 }
 
 /**
- *  Finds the smallest positive initial value for register A that causes the
- *  program to output a copy of itself (a quine)
+ *  Number of bits register A loses per loop iteration. The reverse search needs the
+ *  whole program to be one loop ending in `jnz 0`, with a single `out` and a single
+ *  `adv` by a literal shift of 1‑3 before it; anything else is rejected.
  */
-fn find_quine_value(init_b: i128, init_c: i128, prog: &[u8]) -> Result<i128> {
+fn quine_shift(prog: &[u8]) -> Result<u32> {
+  let instrs: Vec<&[u8]> = prog.chunks(2).collect();
+  let Some((&[3, 0], body)) = instrs.split_last() else {
+    bail!("reverse search needs the program to end with `jnz 0` looping back to the start");
+  };
+
+  let count = |opcode: u8| body.iter().filter(|instr| instr[0] == opcode).count();
+  if count(3) > 0 {
+    bail!("reverse search needs the final `jnz 0` to be the only jump");
+  }
+  if count(5) != 1 {
+    bail!("reverse search needs exactly one out instruction in the program loop");
+  }
+
+  let mut advs = body.iter().filter(|instr| instr[0] == 0);
+  let (Some(adv), None) = (advs.next(), advs.next()) else {
+    bail!("reverse search needs exactly one adv instruction in the program loop");
+  };
+  match adv[1] {
+    shift @ 1..=3 => Ok(shift as u32),
+    0 => bail!("adv 0 never shrinks A, so the loop can't end"),
+    op @ 4..=6 => bail!(
+      "adv {} shifts by a register, not a constant number of bits",
+      combo_name(op)
+    ),
+    op => bail!("adv operand {op} is reserved"),
+  }
+}
+
+/**
+ *  Runs the reverse search digit by digit and returns every initial value of
+ *  register A whose loop iterations emit the program, one digit each
+ */
+fn quine_candidates(init_b: i128, init_c: i128, prog: &[u8]) -> Result<HashSet<i128>> {
+  output_candidates(init_b, init_c, prog, prog)
+}
+
+/**
+ *  Every initial value of register A whose loop iterations emit `target`, one digit
+ *  each, found by extending A backwards `quine_shift` bits at a time
+ */
+fn output_candidates(
+  init_b: i128,
+  init_c: i128,
+  prog: &[u8],
+  target: &[u8],
+) -> Result<HashSet<i128>> {
+  let shift = quine_shift(prog)?;
+
   // Each element represents a possible value of A *after* one iteration
   let mut frontier: HashSet<i128> = [0].into_iter().collect();
 
-  // Work backwards through the program digits
-  for (step, &required_digit) in prog.iter().rev().enumerate() {
+  // Work backwards through the target digits
+  for (step, &required_digit) in target.iter().rev().enumerate() {
     let mut next_frontier = HashSet::new();
 
     for &next_a in &frontier {
-      // Try all possible 3-bit extensions (since A is typically divided by 8 each iteration)
-      for r in 0..8 {
-        let candidate_a = next_a * 8 + r;
+      // Try every value of the low bits that the iteration's `adv` shifts away
+      for r in 0..1 << shift {
+        let candidate_a = (next_a << shift) | r;
+        // A = 0 would already have halted, so it can't start an iteration
+        if candidate_a == 0 {
+          continue;
+        }
 
         // Test if this candidate produces the required digit and transitions to next_a
         match step_once(candidate_a, init_b, init_c, prog) {
@@ -225,16 +397,19 @@ fn find_quine_value(init_b: i128, init_c: i128, prog: &[u8]) -> Result<i128> {
     frontier = next_frontier;
   }
 
-  // Get the minimum candidate
-  let best_a = *frontier.iter().min().context("No valid candidates found")?;
+  Ok(frontier)
+}
 
-  // Validate the solution by running the complete program
+/**
+ *  Checks that running the program from `a` reproduces the program exactly
+ */
+fn validate_quine(a: i128, init_b: i128, init_c: i128, prog: &[u8]) -> Result<()> {
   let test_regs = Regs {
-    a: best_a,
+    a,
     b: init_b,
     c: init_c,
   };
-  let full_output = exec(test_regs, prog)?;
+  let full_output = exec(test_regs, prog, INSTRUCTION_BUDGET)?;
 
   if full_output.len() != prog.len() || full_output != prog {
     bail!(
@@ -244,11 +419,50 @@ fn find_quine_value(init_b: i128, init_c: i128, prog: &[u8]) -> Result<i128> {
     );
   }
 
+  Ok(())
+}
+
+/**
+ *  Finds the smallest positive initial value for register A that causes the
+ *  program to output a copy of itself (a quine)
+ */
+fn find_quine_value(init_b: i128, init_c: i128, prog: &[u8]) -> Result<i128> {
+  let frontier = quine_candidates(init_b, init_c, prog)?;
+
+  // Get the minimum candidate
+  let best_a = *frontier.iter().min().context("No valid candidates found")?;
+
+  // Validate the solution by running the complete program
+  validate_quine(best_a, init_b, init_c, prog)?;
+
   Ok(best_a)
 }
 
+/**
+ *  Every quine value of register A below `limit`, in ascending order
+ *  (the first one is what `find_quine_value` returns)
+ */
+pub fn find_all_quine_values(
+  init_b: i128,
+  init_c: i128,
+  prog: &[u8],
+  limit: i128,
+) -> Result<Vec<i128>> {
+  let mut values: Vec<i128> = quine_candidates(init_b, init_c, prog)?
+    .into_iter()
+    .filter(|&a| a < limit)
+    .collect();
+  values.sort_unstable();
+
+  for &a in &values {
+    validate_quine(a, init_b, init_c, prog)?;
+  }
+
+  Ok(values)
+}
+
 fn infer_program_output(regs: Regs, prog: &[u8]) -> String {
-  exec(regs, prog)
+  exec(regs, prog, INSTRUCTION_BUDGET)
     .unwrap()
     .into_iter()
     .map(|d| d.to_string())
@@ -256,7 +470,7 @@ fn infer_program_output(regs: Regs, prog: &[u8]) -> String {
     .join(",")
 }
 
-fn solve(input: &str, part: u8) -> String {
+pub(crate) fn solve(input: &str, part: u8) -> String {
   let (init_regs, prog) = parse_input(input).expect("Failed to parse input");
 
   match part {
@@ -268,7 +482,7 @@ fn solve(input: &str, part: u8) -> String {
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
   println!("Part 1 result = {}", solve(&input, 1));
@@ -276,8 +490,197 @@ fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day17_simple.txt", "Simple puzzle")?;
   print_result("input/day17_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day17_simple.txt");
+
+  /// bst A, bxl 3, cdv B, bxc, out B, adv 2, jnz 0: like the puzzle inputs, but only
+  /// two bits of A are consumed per digit
+  const TWO_BIT_PROGRAM: [u8; 14] = [2, 4, 1, 3, 7, 5, 4, 0, 5, 5, 0, 2, 3, 0];
+
+  fn regs(a: i128) -> Regs {
+    Regs { a, b: 0, c: 0 }
+  }
+
+  #[test]
+  fn sample_answers() {
+    assert_eq!(solve(SAMPLE, 1), "4,6,3,5,6,3,5,2,1,0");
+    // adv 1 followed by out A has no quine
+    assert_eq!(solve(SAMPLE, 2), "No quine value found");
+  }
+
+  #[test]
+  fn steps_through_the_sample() {
+    let (regs, prog) = parse_input(SAMPLE).unwrap();
+    let mut cpu = Cpu::new(regs, &prog);
+
+    assert_eq!(cpu.step().unwrap(), None); // adv 1
+    assert_eq!((cpu.regs.a, cpu.pc), (364, 2));
+    assert_eq!(cpu.step().unwrap(), Some(4)); // out A
+    assert_eq!(cpu.pc, 4);
+    assert_eq!(cpu.step().unwrap(), None); // jnz 0
+    assert_eq!((cpu.regs.a, cpu.pc), (364, 0));
+    assert_eq!(cpu.out, [4]);
+
+    while !cpu.halted() {
+      cpu.step().unwrap();
+    }
+    assert_eq!(cpu.regs.a, 0);
+    assert_eq!(cpu.out, [4, 6, 3, 5, 6, 3, 5, 2, 1, 0]);
+
+    // Stepping a halted CPU changes nothing
+    assert_eq!(cpu.step().unwrap(), None);
+    assert_eq!(cpu.out.len(), 10);
+  }
+
+  #[test]
+  fn step_reports_bad_instructions() {
+    assert!(Cpu::new(regs(1), &[9, 0]).step().is_err());
+    assert!(Cpu::new(regs(1), &[5]).step().is_err());
+    assert!(Cpu::new(regs(1), &[0, 4]).step().is_ok());
+    assert!(
+      Cpu::new(Regs { a: 1, b: 127, c: 0 }, &[0, 5])
+        .step()
+        .is_err()
+    );
+  }
+
+  #[test]
+  fn traces_the_sample() {
+    let (regs, prog) = parse_input(SAMPLE).unwrap();
+    let trace = exec_trace(regs, &prog).unwrap();
+
+    // 10 loop iterations of 3 instructions
+    assert_eq!(trace.len(), 30);
+    let (pc, first_regs, emitted) = trace[0];
+    assert_eq!((pc, first_regs.a, emitted), (0, 364, None));
+    let (pc, last_regs, emitted) = trace[29];
+    assert_eq!((pc, last_regs.a, last_regs.b, last_regs.c), (4, 0, 0, 0));
+    assert_eq!(emitted, None);
+
+    let output: Vec<u8> = trace.iter().filter_map(|&(_, _, digit)| digit).collect();
+    assert_eq!(output, exec(regs, &prog, INSTRUCTION_BUDGET).unwrap());
+  }
+
+  #[test]
+  fn budget_stops_self_jumps() {
+    // jnz 0 with A != 0 spins forever
+    let err = exec(regs(1), &[3, 0], 1000).unwrap_err();
+    assert!(err.to_string().contains("did not halt within 1000"));
+    // with A = 0 it falls through and halts
+    assert_eq!(exec(regs(0), &[3, 0], 1000).unwrap(), []);
+
+    // The sample needs exactly 30 instructions
+    let (regs, prog) = parse_input(SAMPLE).unwrap();
+    assert!(exec(regs, &prog, 29).is_err());
+    assert_eq!(exec(regs, &prog, 30).unwrap().len(), 10);
+  }
+
+  #[test]
+  fn all_quine_values_start_with_the_smallest() {
+    // The part 2 example from the puzzle text
+    let prog = [0, 3, 5, 4, 3, 0];
+    assert_eq!(find_quine_value(0, 0, &prog).unwrap(), 117440);
+
+    let all = find_all_quine_values(0, 0, &prog, i128::MAX).unwrap();
+    assert_eq!(all[0], 117440);
+    assert!(all.is_sorted());
+    for &a in &all {
+      assert_eq!(exec(regs(a), &prog, INSTRUCTION_BUDGET).unwrap(), prog);
+    }
+
+    assert_eq!(
+      find_all_quine_values(0, 0, &prog, 117441).unwrap(),
+      [117440]
+    );
+    assert!(
+      find_all_quine_values(0, 0, &prog, 117440)
+        .unwrap()
+        .is_empty()
+    );
+  }
+
+  #[test]
+  fn disassembles_the_sample() {
+    let (_, prog) = parse_input(SAMPLE).unwrap();
+    assert_eq!(
+      disassemble(&prog),
+      [
+        "0: adv 1 ; A = A >> 1",
+        "2: out A ; output A % 8",
+        "4: jnz 0 ; if A != 0 jump to 0",
+      ]
+    );
+  }
+
+  #[test]
+  fn disassembles_every_opcode() {
+    assert_eq!(
+      disassemble(&TWO_BIT_PROGRAM),
+      [
+        "0: bst A ; B = A % 8",
+        "2: bxl 3 ; B = B ^ 3",
+        "4: cdv B ; C = A >> B",
+        "6: bxc 0 ; B = B ^ C",
+        "8: out B ; output B % 8",
+        "10: adv 2 ; A = A >> 2",
+        "12: jnz 0 ; if A != 0 jump to 0",
+      ]
+    );
+    assert_eq!(
+      disassemble(&[6, 7, 8, 1, 5]),
+      [
+        "0: bdv <reserved 7> ; B = A >> <reserved 7>",
+        "2: <unknown opcode 8> 1",
+        "4: <dangling opcode 5>",
+      ]
+    );
+  }
+
+  #[test]
+  fn quine_shift_reads_the_loop_adv() {
+    assert_eq!(quine_shift(&TWO_BIT_PROGRAM).unwrap(), 2);
+    assert_eq!(quine_shift(&[0, 3, 5, 4, 3, 0]).unwrap(), 3);
+  }
+
+  #[test]
+  fn quine_shift_rejects_unsupported_loops() {
+    let err = |prog: &[u8]| quine_shift(prog).unwrap_err().to_string();
+    // adv after the loop
+    assert!(err(&[5, 4, 3, 0, 0, 3]).contains("jnz 0"));
+    // loop back to somewhere other than the start
+    assert!(err(&[0, 3, 5, 4, 3, 2]).contains("jnz 0"));
+    assert!(err(&[0, 3, 3, 0, 5, 4, 3, 0]).contains("only jump"));
+    assert!(err(&[0, 3, 5, 4, 5, 4, 3, 0]).contains("one out"));
+    assert!(err(&[0, 3, 0, 1, 5, 4, 3, 0]).contains("one adv"));
+    assert!(err(&[2, 4, 0, 5, 5, 4, 3, 0]).contains("adv B shifts by a register"));
+    assert!(err(&[0, 0, 5, 4, 3, 0]).contains("never shrinks"));
+  }
+
+  #[test]
+  fn two_bit_search_matches_brute_force() {
+    let prog = TWO_BIT_PROGRAM;
+    let target = exec(regs(3000), &prog, INSTRUCTION_BUDGET).unwrap();
+    assert_eq!(target.len(), 6);
+
+    // Six iterations consume 12 bits, so every answer is below 4^6
+    let expected: Vec<i128> = (1..1 << 12)
+      .filter(|&a| exec(regs(a), &prog, INSTRUCTION_BUDGET).unwrap() == target)
+      .collect();
+    let mut found: Vec<i128> = output_candidates(0, 0, &prog, &target)
+      .unwrap()
+      .into_iter()
+      .collect();
+    found.sort_unstable();
+    assert_eq!(found, expected);
+    assert!(found.contains(&3000));
+  }
+}
@@ -2,7 +2,8 @@ use anyhow::Result;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 
-fn can_form_design(
+// Hash-set scan over every pattern at each position; the trie versions are used instead
+pub fn can_form_design(
   design: &str,
   patterns: &HashSet<String>,
   memo: &mut HashMap<String, bool>,
@@ -29,7 +30,7 @@ fn can_form_design(
   false
 }
 
-fn count_ways(
+pub fn count_ways(
   design: &str,
   patterns: &HashSet<String>,
   memo: &mut HashMap<String, usize>,
@@ -54,11 +55,181 @@ fn count_ways(
   total_ways
 }
 
+#[derive(Default)]
+struct TrieNode {
+  children: HashMap<u8, usize>,
+  terminal: bool, // a pattern ends here
+}
+
+/// All towel patterns merged into one prefix tree, so matching at a position only follows
+/// the patterns that actually share the design's next stripes.
+pub struct PatternTrie {
+  nodes: Vec<TrieNode>,
+}
+
+impl PatternTrie {
+  pub fn new(patterns: &HashSet<String>) -> Self {
+    let mut trie = Self {
+      nodes: vec![TrieNode::default()],
+    };
+    for pattern in patterns {
+      trie.insert(pattern);
+    }
+    trie
+  }
+
+  fn insert(&mut self, pattern: &str) {
+    let mut node = 0;
+    for byte in pattern.bytes() {
+      node = match self.nodes[node].children.get(&byte) {
+        Some(&child) => child,
+        None => {
+          self.nodes.push(TrieNode::default());
+          let child = self.nodes.len() - 1;
+          self.nodes[node].children.insert(byte, child);
+          child
+        }
+      };
+    }
+    self.nodes[node].terminal = true;
+  }
+
+  /// Lengths of every pattern that is a prefix of `design`, shortest first
+  fn prefix_lengths<'a>(&'a self, design: &'a str) -> impl Iterator<Item = usize> + 'a {
+    design
+      .bytes()
+      .scan(0, |node, byte| {
+        *node = *self.nodes[*node].children.get(&byte)?;
+        Some(*node)
+      })
+      .enumerate()
+      .filter(|&(_, node)| self.nodes[node].terminal)
+      .map(|(i, _)| i + 1)
+  }
+}
+
+fn can_form_design_trie(
+  design: &str,
+  trie: &PatternTrie,
+  memo: &mut HashMap<String, bool>,
+) -> bool {
+  if design.is_empty() {
+    return true;
+  }
+
+  if let Some(&result) = memo.get(design) {
+    return result;
+  }
+
+  for len in trie.prefix_lengths(design) {
+    if can_form_design_trie(&design[len..], trie, memo) {
+      memo.insert(design.to_string(), true);
+      return true;
+    }
+  }
+
+  memo.insert(design.to_string(), false);
+  false
+}
+
+pub fn count_ways_trie(
+  design: &str,
+  trie: &PatternTrie,
+  memo: &mut HashMap<String, usize>,
+) -> usize {
+  if design.is_empty() {
+    return 1; // One way to form empty string
+  }
+
+  if let Some(&result) = memo.get(design) {
+    return result;
+  }
+
+  let total_ways = trie
+    .prefix_lengths(design)
+    .map(|len| count_ways_trie(&design[len..], trie, memo))
+    .sum();
+
+  memo.insert(design.to_string(), total_ways);
+  total_ways
+}
+
+/// Bottom-up version of `count_ways_trie`: `ways[i]` counts the constructions of the
+/// suffix starting at `i`, so there is no recursion and no string-keyed memo.
+pub fn count_ways_dp(design: &str, patterns: &PatternTrie) -> u128 {
+  let n = design.len();
+  let mut ways = vec![0u128; n + 1];
+  ways[n] = 1; // One way to form the empty suffix
+
+  for start in (0..n).rev() {
+    ways[start] = patterns
+      .prefix_lengths(&design[start..])
+      .map(|len| ways[start + len])
+      .sum();
+  }
+
+  ways[0]
+}
+
+/// One concrete sequence of patterns that spells out `design`, if it can be formed.
+/// Fills a "suffix from i is formable" table right to left, then walks it forwards
+/// taking any pattern that lands on a formable suffix.
+pub fn example_construction(design: &str, patterns: &HashSet<String>) -> Option<Vec<String>> {
+  let trie = PatternTrie::new(patterns);
+  let n = design.len();
+
+  let mut formable = vec![false; n + 1];
+  formable[n] = true;
+  for start in (0..n).rev() {
+    formable[start] = trie
+      .prefix_lengths(&design[start..])
+      .any(|len| formable[start + len]);
+  }
+
+  if !formable[0] {
+    return None;
+  }
+
+  let mut pieces = Vec::new();
+  let mut start = 0;
+  while start < n {
+    let len = trie
+      .prefix_lengths(&design[start..])
+      .find(|&len| formable[start + len])?;
+    pieces.push(design[start..start + len].to_string());
+    start += len;
+  }
+
+  Some(pieces)
+}
+
+/// Patterns that can't be stitched together from the others. Dropping the rest never
+/// changes whether a design can be formed, so this is safe for part 1 only: the dropped
+/// patterns still add distinct constructions to the part 2 counts.
+pub fn minimal_pattern_set(patterns: &HashSet<String>) -> HashSet<String> {
+  // A composite pattern is built from strictly shorter ones, so deciding them shortest
+  // first means every piece it could use has already been kept or dropped
+  let mut by_length: Vec<&String> = patterns.iter().collect();
+  by_length.sort_by_key(|pattern| pattern.len());
+
+  let mut kept = HashSet::new();
+  let mut trie = PatternTrie::new(&kept);
+  for pattern in by_length {
+    if !can_form_design_trie(pattern, &trie, &mut HashMap::new()) {
+      trie.insert(pattern);
+      kept.insert(pattern.clone());
+    }
+  }
+
+  kept
+}
+
 fn count_possible_designs(designs: &[&str], patterns: &HashSet<String>) -> usize {
+  let trie = PatternTrie::new(&minimal_pattern_set(patterns));
   let mut count = 0;
   for design in designs {
     let mut memo = HashMap::new();
-    if can_form_design(design, patterns, &mut memo) {
+    if can_form_design_trie(design, &trie, &mut memo) {
       count += 1;
     }
   }
@@ -66,29 +237,32 @@ fn count_possible_designs(designs: &[&str], patterns: &HashSet<String>) -> usize
   count
 }
 
-fn count_possible_constructions_for_designs(designs: &[&str], patterns: &HashSet<String>) -> usize {
-  let mut total_ways = 0;
-  for design in designs {
-    let mut memo = HashMap::new();
-    total_ways += count_ways(design, patterns, &mut memo);
-  }
+/// Number of constructions for each design, in input order.
+pub fn construction_counts(designs: &[&str], patterns: &HashSet<String>) -> Vec<u128> {
+  let trie = PatternTrie::new(patterns);
+  designs
+    .iter()
+    .map(|design| count_ways_dp(design, &trie))
+    .collect()
+}
 
-  total_ways
+fn count_possible_constructions_for_designs(designs: &[&str], patterns: &HashSet<String>) -> u128 {
+  construction_counts(designs, patterns).iter().sum()
 }
 
-fn solve(input: &str, part: u8) -> usize {
+pub(crate) fn solve(input: &str, part: u8) -> u128 {
   let lines: Vec<&str> = input.trim().split('\n').collect();
   let patterns: HashSet<String> = lines[0].split(", ").map(|s| s.to_string()).collect();
   let designs: Vec<&str> = lines[2..].to_vec();
 
   match part {
-    1 => count_possible_designs(&designs, &patterns),
+    1 => count_possible_designs(&designs, &patterns) as u128,
     2 => count_possible_constructions_for_designs(&designs, &patterns),
     _ => panic!("Only part 1 or 2 is possible."),
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
   println!("Part 1 result = {}", solve(&input, 1));
@@ -96,8 +270,140 @@ fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day19_simple.txt", "Simple puzzle")?;
   print_result("input/day19_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day19_simple.txt");
+
+  fn sample() -> (HashSet<String>, Vec<&'static str>) {
+    let mut lines = SAMPLE.lines();
+    let patterns = lines
+      .next()
+      .unwrap()
+      .split(", ")
+      .map(String::from)
+      .collect();
+    (patterns, lines.filter(|line| !line.is_empty()).collect())
+  }
+
+  #[test]
+  fn sample_answers() {
+    assert_eq!(solve(SAMPLE, 1), 6);
+    assert_eq!(solve(SAMPLE, 2), 16);
+  }
+
+  #[test]
+  fn trie_matches_hash_set_scan() {
+    let (patterns, designs) = sample();
+    let trie = PatternTrie::new(&patterns);
+    for design in designs {
+      assert_eq!(
+        can_form_design_trie(design, &trie, &mut HashMap::new()),
+        can_form_design(design, &patterns, &mut HashMap::new()),
+        "{design}"
+      );
+      assert_eq!(
+        count_ways_trie(design, &trie, &mut HashMap::new()),
+        count_ways(design, &patterns, &mut HashMap::new()),
+        "{design}"
+      );
+    }
+  }
+
+  #[test]
+  fn example_constructions_spell_the_design() {
+    let (patterns, designs) = sample();
+    for design in designs {
+      match example_construction(design, &patterns) {
+        Some(pieces) => {
+          assert_eq!(pieces.concat(), design);
+          assert!(pieces.iter().all(|piece| patterns.contains(piece)));
+        }
+        None => assert!(["ubwu", "bbrgwb"].contains(&design)),
+      }
+    }
+    assert_eq!(example_construction("", &patterns), Some(Vec::new()));
+  }
+
+  #[test]
+  fn bottom_up_dp_matches_recursion() {
+    let (patterns, designs) = sample();
+    let trie = PatternTrie::new(&patterns);
+    for design in designs {
+      let recursive = count_ways_trie(design, &trie, &mut HashMap::new());
+      assert_eq!(count_ways_dp(design, &trie), recursive as u128, "{design}");
+    }
+  }
+
+  #[test]
+  fn bottom_up_dp_handles_long_designs() {
+    // Fibonacci many constructions: past u64 after 100 stripes
+    let patterns = ["r", "rr"].map(String::from).into();
+    let trie = PatternTrie::new(&patterns);
+    let fib = (0..100).fold((1u128, 1u128), |(a, b), _| (b, a + b)).0;
+    assert!(fib > u64::MAX as u128);
+    assert_eq!(count_ways_dp(&"r".repeat(100), &trie), fib);
+
+    // Far deeper than the recursive version's call stack would allow
+    let patterns = ["r"].map(String::from).into();
+    let trie = PatternTrie::new(&patterns);
+    assert_eq!(count_ways_dp(&"r".repeat(100_000), &trie), 1);
+  }
+
+  #[test]
+  fn per_design_counts_sum_to_part_two() {
+    let (patterns, designs) = sample();
+    let counts = construction_counts(&designs, &patterns);
+    assert_eq!(
+      designs,
+      [
+        "brwrr", "bggr", "gbbr", "rrbgbr", "ubwu", "bwurrg", "brgr", "bbrgwb"
+      ]
+    );
+    assert_eq!(counts, [2, 1, 4, 6, 0, 1, 2, 0]);
+    assert_eq!(
+      counts.iter().sum::<u128>(),
+      count_possible_constructions_for_designs(&designs, &patterns)
+    );
+  }
+
+  #[test]
+  fn redundant_patterns_only_matter_for_counts() {
+    let (patterns, designs) = sample();
+    let minimal = minimal_pattern_set(&patterns);
+
+    // "rb" = "r" + "b", "br" = "b" + "r" and "gb" = "g" + "b"
+    let mut dropped: Vec<&String> = patterns.difference(&minimal).collect();
+    dropped.sort_unstable();
+    assert_eq!(dropped, ["br", "gb", "rb"]);
+
+    let full = PatternTrie::new(&patterns);
+    let reduced = PatternTrie::new(&minimal);
+    for design in &designs {
+      assert_eq!(
+        can_form_design_trie(design, &reduced, &mut HashMap::new()),
+        can_form_design_trie(design, &full, &mut HashMap::new()),
+        "{design}"
+      );
+    }
+    assert_ne!(
+      construction_counts(&designs, &minimal),
+      construction_counts(&designs, &patterns)
+    );
+  }
+
+  #[test]
+  fn prefix_lengths_are_shortest_first() {
+    let patterns = ["b", "br", "bwu", "brw"].map(String::from).into();
+    let trie = PatternTrie::new(&patterns);
+    assert_eq!(trie.prefix_lengths("brwrr").collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(trie.prefix_lengths("gb").count(), 0);
+  }
+}
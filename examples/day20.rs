@@ -1,15 +1,15 @@
 use anyhow::Result;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fs;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Point {
-  row: usize,
-  col: usize,
+pub struct Point {
+  pub row: usize,
+  pub col: usize,
 }
 
 impl Point {
-  fn new(row: usize, col: usize) -> Self {
+  pub fn new(row: usize, col: usize) -> Self {
     Self { row, col }
   }
 
@@ -96,8 +96,71 @@ fn find_path(grid: &[Vec<char>], start: Point, end: Point) -> Vec<Point> {
   path
 }
 
-fn solve_with_cheat_limit(input: &str, min_savings: usize, max_cheat_time: usize) -> usize {
+/// Checks the track is one branch-free corridor from S to E: S and E are its only dead
+/// ends (one track neighbour each), every other track cell has exactly two, and every
+/// track cell lies on the S→E path (so there are no detached loops).
+pub fn validate_single_path(grid: &[Vec<char>]) -> Result<(), String> {
+  let mut start = None;
+  let mut end = None;
+  let mut track_cells = 0;
+
+  for (row, line) in grid.iter().enumerate() {
+    for (col, &ch) in line.iter().enumerate() {
+      let pos = Point::new(row, col);
+      if !is_track(grid, pos) {
+        continue;
+      }
+      track_cells += 1;
+
+      let slot = match ch {
+        'S' => Some(&mut start),
+        'E' => Some(&mut end),
+        _ => None,
+      };
+      let endpoint = slot.is_some();
+      if let Some(slot) = slot
+        && slot.replace(pos).is_some()
+      {
+        return Err(format!("second '{ch}' at {pos:?}"));
+      }
+
+      let degree = pos
+        .neighbors()
+        .into_iter()
+        .filter(|&neighbor| is_track(grid, neighbor))
+        .count();
+      let expected = if endpoint { 1 } else { 2 };
+      if degree != expected {
+        return Err(format!(
+          "track cell {pos:?} has {degree} neighbours, expected {expected}"
+        ));
+      }
+    }
+  }
+
+  let (Some(start), Some(end)) = (start, end) else {
+    return Err("track needs both a start and an end".to_string());
+  };
+  let path = find_path(grid, start, end);
+  if path.first() != Some(&start) || path.len() != track_cells {
+    return Err(format!(
+      "S→E path covers {} of {track_cells} track cells",
+      path.len()
+    ));
+  }
+
+  Ok(())
+}
+
+// Path-index version for single-path tracks; see `solve_with_distance_maps`
+pub fn solve_with_cheat_limit(
+  input: &str,
+  min_savings: usize,
+  max_cheat_time: usize,
+) -> Result<usize, String> {
   let (grid, start, end) = parse_input(input);
+  // Path indices only measure distance when there is nothing but the one path
+  validate_single_path(&grid)?;
   let path = find_path(&grid, start, end);
 
   // Create a map from position to index in path
@@ -129,19 +192,18 @@ fn solve_with_cheat_limit(input: &str, min_savings: usize, max_cheat_time: usize
         let cheat_end = Point::new(cheat_end_row as usize, cheat_end_col as usize);
 
         // Check if cheat_end is a valid track position and on the path
-        if is_track(&grid, cheat_end) {
-          if let Some(&end_idx) = pos_to_index.get(&cheat_end) {
-            if end_idx > start_idx {
-              let normal_dist = end_idx - start_idx;
-              let cheat_dist = manhattan_dist as usize;
-
-              if normal_dist > cheat_dist {
-                let time_saved = normal_dist - cheat_dist;
-
-                if time_saved >= min_savings {
-                  cheat_count += 1;
-                }
-              }
+        if is_track(&grid, cheat_end)
+          && let Some(&end_idx) = pos_to_index.get(&cheat_end)
+          && end_idx > start_idx
+        {
+          let normal_dist = end_idx - start_idx;
+          let cheat_dist = manhattan_dist as usize;
+
+          if normal_dist > cheat_dist {
+            let time_saved = normal_dist - cheat_dist;
+
+            if time_saved >= min_savings {
+              cheat_count += 1;
             }
           }
         }
@@ -149,29 +211,259 @@ fn solve_with_cheat_limit(input: &str, min_savings: usize, max_cheat_time: usize
     }
   }
 
-  cheat_count
+  Ok(cheat_count)
+}
+
+/// BFS distance from `source` to every reachable track cell (`None` for walls/unreached)
+fn distances_from(grid: &[Vec<char>], source: Point) -> Vec<Vec<Option<usize>>> {
+  let mut dist = vec![vec![None; grid[0].len()]; grid.len()];
+  let mut queue = VecDeque::new();
+
+  dist[source.row][source.col] = Some(0);
+  queue.push_back(source);
+
+  while let Some(current) = queue.pop_front() {
+    let next_dist = dist[current.row][current.col].unwrap() + 1;
+    for neighbor in current.neighbors() {
+      if is_track(grid, neighbor) && dist[neighbor.row][neighbor.col].is_none() {
+        dist[neighbor.row][neighbor.col] = Some(next_dist);
+        queue.push_back(neighbor);
+      }
+    }
+  }
+
+  dist
+}
+
+/// Same count as `solve_with_cheat_limit`, without treating the track as one path.
+///
+/// The path-index version is sound only for branch-free tracks: there every track cell is
+/// on the single S→E path, its index equals its distance from S, and a cheat from index i
+/// to j > i saves `(j - i) - cheat_len`. Cheats to j < i run backwards and never help.
+///
+/// Here a cheat a→b instead costs `from_start[a] + cheat_len + to_end[b]`, and saves the
+/// difference to the honest best time. On a single path `from_start[a] = i` and
+/// `to_end[b] = best - j`, so both agree; on a branching map this stays correct.
+fn solve_with_distance_maps(input: &str, min_savings: usize, max_cheat_time: usize) -> usize {
+  find_cheats(input, min_savings, max_cheat_time).len()
+}
+
+/// Cheats saving at least `min_savings`, as (cheat start, cheat end, time saved)
+pub fn find_cheats(
+  input: &str,
+  min_savings: usize,
+  max_cheat_time: usize,
+) -> Vec<(Point, Point, usize)> {
+  cheat_savings(input, max_cheat_time)
+    .into_iter()
+    .filter(|&(_, _, saved)| saved >= min_savings)
+    .collect()
+}
+
+/// Every cheat that saves time, as (cheat start, cheat end, time saved)
+fn cheat_savings(input: &str, max_cheat_time: usize) -> Vec<(Point, Point, usize)> {
+  let (grid, start, end) = parse_input(input);
+  let from_start = distances_from(&grid, start);
+  let to_end = distances_from(&grid, end);
+  let Some(best) = from_start[end.row][end.col] else {
+    return Vec::new();
+  };
+
+  let max_dist = max_cheat_time as isize;
+  let mut cheats = Vec::new();
+
+  for (row, line) in from_start.iter().enumerate() {
+    for (col, &before) in line.iter().enumerate() {
+      let Some(before) = before else {
+        continue;
+      };
+
+      for dr in -max_dist..=max_dist {
+        let budget = max_dist - dr.abs();
+        for dc in -budget..=budget {
+          let (end_row, end_col) = (row as isize + dr, col as isize + dc);
+          if end_row < 0 || end_col < 0 {
+            continue;
+          }
+          let cheat_end = Point::new(end_row as usize, end_col as usize);
+          if !is_valid_pos(&grid, cheat_end) {
+            continue;
+          }
+
+          let Some(after) = to_end[cheat_end.row][cheat_end.col] else {
+            continue;
+          };
+          let cheat_time = before + (dr.abs() + dc.abs()) as usize + after;
+          if cheat_time < best {
+            cheats.push((Point::new(row, col), cheat_end, best - cheat_time));
+          }
+        }
+      }
+    }
+  }
+
+  cheats
 }
 
-fn solve(input: &str, part: u8) -> usize {
-  let min_savings = 100;
+/// How many cheats save each amount of time, e.g. for the sample with 2-picosecond
+/// cheats `{2: 14, 4: 14, 6: 2, 8: 4, 10: 2, 12: 3, 20: 1, 36: 1, 38: 1, 40: 1, 64: 1}`
+pub fn savings_histogram(input: &str, max_cheat_time: usize) -> BTreeMap<usize, usize> {
+  let mut histogram = BTreeMap::new();
+  for (_, _, saved) in cheat_savings(input, max_cheat_time) {
+    *histogram.entry(saved).or_insert(0) += 1;
+  }
+  histogram
+}
+
+/// Default time a cheat must save to be counted
+pub(crate) const MIN_SAVINGS: usize = 100;
+
+/// Cheats saving at least `min_savings`: up to 2 picoseconds long for part 1, 20 for part 2
+pub(crate) fn solve(input: &str, min_savings: usize, part: u8) -> usize {
   let cheat_limit = match part {
     1 => 2,
     2 => 20,
     _ => panic!("Only part 1 or 2 is possible."),
   };
-  solve_with_cheat_limit(input, min_savings, cheat_limit)
+  solve_with_distance_maps(input, min_savings, cheat_limit)
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
-  println!("Part 1 result = {}", solve(&input, 1));
-  println!("Part 2 result = {}\n", solve(&input, 2));
+  println!("Part 1 result = {}", solve(&input, MIN_SAVINGS, 1));
+  println!("Part 2 result = {}\n", solve(&input, MIN_SAVINGS, 2));
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day20_simple.txt", "Simple puzzle")?;
   print_result("input/day20_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day20_simple.txt");
+
+  #[test]
+  fn smaller_thresholds() {
+    // The sample track is too short for the default threshold
+    assert_eq!(solve(SAMPLE, MIN_SAVINGS, 1), 0);
+    assert_eq!(solve(SAMPLE, MIN_SAVINGS, 2), 0);
+    assert_eq!(solve(SAMPLE, 50, 1), 1);
+    assert_eq!(solve(SAMPLE, 50, 2), 285);
+  }
+
+  #[test]
+  fn distance_maps_match_path_indices() {
+    for (min_savings, max_cheat_time) in [(1, 2), (20, 2), (50, 20), (76, 20)] {
+      assert_eq!(
+        solve_with_distance_maps(SAMPLE, min_savings, max_cheat_time),
+        solve_with_cheat_limit(SAMPLE, min_savings, max_cheat_time).unwrap()
+      );
+    }
+    assert_eq!(solve_with_distance_maps(SAMPLE, 1, 2), 44);
+  }
+
+  #[test]
+  fn finds_the_best_cheat_endpoints() {
+    // The sample's best 2-picosecond cheat cuts through the wall left of E
+    assert_eq!(
+      find_cheats(SAMPLE, 64, 2),
+      [(Point::new(7, 7), Point::new(7, 5), 64)]
+    );
+    assert_eq!(find_cheats(SAMPLE, 65, 2), []);
+    for min_savings in [1, 10, 40] {
+      assert_eq!(
+        find_cheats(SAMPLE, min_savings, 2).len(),
+        solve(SAMPLE, min_savings, 1)
+      );
+    }
+  }
+
+  #[test]
+  fn savings_histogram_matches_the_puzzle_tables() {
+    let short = savings_histogram(SAMPLE, 2);
+    let expected = [
+      (2, 14),
+      (4, 14),
+      (6, 2),
+      (8, 4),
+      (10, 2),
+      (12, 3),
+      (20, 1),
+      (36, 1),
+      (38, 1),
+      (40, 1),
+      (64, 1),
+    ];
+    assert_eq!(short, BTreeMap::from(expected));
+
+    // Cheats of up to 20 picoseconds, from 50 picoseconds saved upwards
+    let long: Vec<(usize, usize)> = savings_histogram(SAMPLE, 20)
+      .into_iter()
+      .filter(|&(saved, _)| saved >= 50)
+      .collect();
+    assert_eq!(
+      long,
+      [
+        (50, 32),
+        (52, 31),
+        (54, 29),
+        (56, 39),
+        (58, 25),
+        (60, 23),
+        (62, 20),
+        (64, 19),
+        (66, 12),
+        (68, 14),
+        (70, 12),
+        (72, 22),
+        (74, 4),
+        (76, 3)
+      ]
+    );
+  }
+
+  #[test]
+  fn validates_single_path_tracks() {
+    let (grid, _, _) = parse_input(SAMPLE);
+    assert_eq!(validate_single_path(&grid), Ok(()));
+
+    let invalid = |input: &str| {
+      let (grid, _, _) = parse_input(input);
+      validate_single_path(&grid).unwrap_err()
+    };
+    // Two ways around the middle wall
+    assert!(invalid("#####\n#S..#\n#.#.#\n#..E#\n#####").contains("neighbours"));
+    // A side branch off the corridor
+    assert!(invalid("#####\n#S.E#\n##.##\n#####").contains("3 neighbours"));
+    // A loop that S→E never visits
+    assert!(
+      invalid("######\n#S.E##\n######\n#...##\n#.#.##\n#...##\n######").contains("covers 3 of 11")
+    );
+    assert!(invalid("#####\n#S.S#\n#####").contains("second 'S'"));
+    assert!(invalid("###\n###").contains("start and an end"));
+  }
+
+  #[test]
+  fn distance_maps_handle_branching_tracks() {
+    // Two ways back from the right-hand side; the short one is 14 picoseconds
+    let branched = "\
+#########
+#S......#
+#######.#
+#E......#
+#.#####.#
+#.......#
+#########";
+    assert!(solve_with_cheat_limit(branched, 1, 2).is_err());
+    assert_eq!(
+      find_cheats(branched, 12, 2),
+      [(Point::new(1, 1), Point::new(3, 1), 12)]
+    );
+  }
+}
@@ -1,114 +1,125 @@
-use anyhow::Result;
-use std::collections::HashMap;
+use anyhow::{Result, bail};
+use std::collections::{HashMap, VecDeque};
 use std::fs;
 
 type Position = (i32, i32);
 
-struct Keypad {
+pub struct Keypad {
   buttons: HashMap<char, Position>,
   gap: Position,
 }
 
 impl Keypad {
-  fn numeric() -> Self {
+  /// Build a keypad from its rows, one character per button; a space marks the gap the
+  /// arm must never point at (only the first space is recorded as `gap`, but every
+  /// non-button cell is avoided when moving).
+  pub fn from_layout(rows: &[&str]) -> Self {
     let mut buttons = HashMap::new();
-    buttons.insert('7', (0, 0));
-    buttons.insert('8', (0, 1));
-    buttons.insert('9', (0, 2));
-    buttons.insert('4', (1, 0));
-    buttons.insert('5', (1, 1));
-    buttons.insert('6', (1, 2));
-    buttons.insert('1', (2, 0));
-    buttons.insert('2', (2, 1));
-    buttons.insert('3', (2, 2));
-    buttons.insert('0', (3, 1));
-    buttons.insert('A', (3, 2));
+    let mut gap = None;
+
+    for (row, line) in rows.iter().enumerate() {
+      for (col, ch) in line.chars().enumerate() {
+        let pos = (row as i32, col as i32);
+        if ch == ' ' {
+          gap.get_or_insert(pos);
+        } else {
+          buttons.insert(ch, pos);
+        }
+      }
+    }
 
     Self {
       buttons,
-      gap: (3, 0),
+      // No gap: park it off the keypad where the arm can never go
+      gap: gap.unwrap_or((-1, -1)),
     }
   }
 
-  fn directional() -> Self {
-    let mut buttons = HashMap::new();
-    buttons.insert('^', (0, 1));
-    buttons.insert('A', (0, 2));
-    buttons.insert('<', (1, 0));
-    buttons.insert('v', (1, 1));
-    buttons.insert('>', (1, 2));
+  pub fn numeric() -> Self {
+    Self::from_layout(&["789", "456", "123", " 0A"])
+  }
 
-    Self {
-      buttons,
-      gap: (0, 0),
-    }
+  pub fn directional() -> Self {
+    Self::from_layout(&[" ^A", "<v>"])
   }
 
+  /// Every shortest path from `from` to `to` that only passes over buttons. Where nothing
+  /// is in the way these are all the interleavings of the needed vertical and horizontal
+  /// moves; around gaps they include the zigzags and detours the straight routes can't.
   fn get_paths(&self, from: char, to: char) -> Vec<String> {
-    if from == to {
-      return vec![String::new()];
-    }
-
-    let (r1, c1) = self.buttons[&from];
-    let (r2, c2) = self.buttons[&to];
-
-    let dr = r2 - r1;
-    let dc = c2 - c1;
-
-    let mut vertical = String::new();
-    let mut horizontal = String::new();
-
-    if dr > 0 {
-      vertical = "v".repeat(dr as usize);
-    } else if dr < 0 {
-      vertical = "^".repeat((-dr) as usize);
-    }
-
-    if dc > 0 {
-      horizontal = ">".repeat(dc as usize);
-    } else if dc < 0 {
-      horizontal = "<".repeat((-dc) as usize);
-    }
-
+    let distances = self.distances_to(self.buttons[&to]);
     let mut paths = Vec::new();
+    self.extend_paths(
+      self.buttons[&from],
+      &distances,
+      &mut String::new(),
+      &mut paths,
+    );
+    paths
+  }
 
-    // Try vertical first, then horizontal
-    if self.is_valid_path((r1, c1), (r2, c2), true) {
-      paths.push(format!("{vertical}{horizontal}"));
+  /// Fewest moves from every button to `to`, passing over buttons only
+  fn distances_to(&self, to: Position) -> HashMap<Position, usize> {
+    let mut distances = HashMap::from([(to, 0)]);
+    let mut queue = VecDeque::from([to]);
+    while let Some((r, c)) = queue.pop_front() {
+      let next_distance = distances[&(r, c)] + 1;
+      for next in [(r + 1, c), (r - 1, c), (r, c + 1), (r, c - 1)] {
+        if self.is_button(next) && !distances.contains_key(&next) {
+          distances.insert(next, next_distance);
+          queue.push_back(next);
+        }
+      }
     }
+    distances
+  }
 
-    // Try horizontal first, then vertical (avoid duplicates)
-    if self.is_valid_path((r1, c1), (r2, c2), false)
-      && !(vertical.is_empty() || horizontal.is_empty())
-    {
-      paths.push(format!("{horizontal}{vertical}"));
+  fn extend_paths(
+    &self,
+    at: Position,
+    distances: &HashMap<Position, usize>,
+    path: &mut String,
+    paths: &mut Vec<String>,
+  ) {
+    let Some(&remaining) = distances.get(&at) else {
+      return; // `to` can't be reached from here
+    };
+    if remaining == 0 {
+      paths.push(path.clone());
+      return;
     }
 
-    if paths.is_empty() {
-      paths.push(format!("{vertical}{horizontal}"));
+    let (r, c) = at;
+    // Vertical moves first, so the first path is the old "vertical then horizontal" one
+    let moves = [
+      ('v', (r + 1, c)),
+      ('^', (r - 1, c)),
+      ('>', (r, c + 1)),
+      ('<', (r, c - 1)),
+    ];
+
+    for (key, next) in moves {
+      if distances.get(&next) == Some(&(remaining - 1)) {
+        path.push(key);
+        self.extend_paths(next, distances, path, paths);
+        path.pop();
+      }
     }
-
-    paths
   }
 
-  fn is_valid_path(&self, from: Position, to: Position, vertical_first: bool) -> bool {
-    let (r1, c1) = from;
-    let (r2, c2) = to;
-
-    if vertical_first {
-      // Check intermediate position after vertical move
-      (r2, c1) != self.gap
-    } else {
-      // Check intermediate position after horizontal move
-      (r1, c2) != self.gap
-    }
+  fn is_button(&self, pos: Position) -> bool {
+    pos != self.gap && self.buttons.values().any(|&button| button == pos)
   }
 }
 
+/// Presses needed at the bottom of the chain to type `sequence` on the keypad at `depth`,
+/// where the robot at `max_depth` points at `top` and every other one at a directional
+/// keypad. `memo` must only be shared between calls with the same `top` and `max_depth`.
 fn min_sequence_length(
   sequence: &str,
   depth: usize,
   max_depth: usize,
+  top: &Keypad,
   memo: &mut HashMap<(String, usize), usize>,
 ) -> usize {
   // Check memoization cache
@@ -122,11 +133,12 @@ fn min_sequence_length(
   }
 
   // Choose keypad based on depth
-  // Numeric keypad is at the maximum depth, all others are directional
+  // The top keypad is at the maximum depth, all others are directional
+  let directional = Keypad::directional();
   let keypad = if depth == max_depth {
-    Keypad::numeric()
+    top
   } else {
-    Keypad::directional()
+    &directional
   };
 
   let mut current_button = 'A';
@@ -140,7 +152,7 @@ fn min_sequence_length(
       .iter()
       .map(|path| {
         let full_sequence = format!("{path}A"); // Add 'A' to press the button
-        min_sequence_length(&full_sequence, depth - 1, max_depth, memo)
+        min_sequence_length(&full_sequence, depth - 1, max_depth, top, memo)
       })
       .min()
       .unwrap_or(0);
@@ -154,53 +166,392 @@ fn min_sequence_length(
   total_length
 }
 
-fn sum_complexities_with_depth(codes: &[&str], depth: usize) -> usize {
-  let mut memo = HashMap::new();
-  let mut total_complexity = 0;
+/// Same recursion as `min_sequence_length`, but builds the presses instead of counting
+/// them: at each level take a cheapest path per button and expand it one level down.
+/// The strings grow exponentially with depth, so this is only meant for small depths.
+fn expand_sequence(
+  sequence: &str,
+  depth: usize,
+  max_depth: usize,
+  top: &Keypad,
+  memo: &mut HashMap<(String, usize), usize>,
+) -> String {
+  if depth == 0 {
+    return sequence.to_string();
+  }
 
-  for code in codes {
-    let sequence_length = min_sequence_length(code, depth, depth, &mut memo);
+  let directional = Keypad::directional();
+  let keypad = if depth == max_depth {
+    top
+  } else {
+    &directional
+  };
 
-    let numeric_part: usize = code
-      .chars()
-      .filter(|c| c.is_ascii_digit())
-      .collect::<String>()
-      .parse()
-      .unwrap_or(0);
+  let mut current_button = 'A';
+  let mut presses = String::new();
+
+  for target_button in sequence.chars() {
+    let best_path = keypad
+      .get_paths(current_button, target_button)
+      .into_iter()
+      .map(|path| format!("{path}A"))
+      .min_by_key(|path| min_sequence_length(path, depth - 1, max_depth, top, memo))
+      .unwrap_or_default();
+
+    presses.push_str(&expand_sequence(
+      &best_path,
+      depth - 1,
+      max_depth,
+      top,
+      memo,
+    ));
+    current_button = target_button;
+  }
 
-    let complexity = sequence_length * numeric_part;
-    total_complexity += complexity;
+  presses
+}
+
+/// One shortest sequence of human presses that types `code` through `depth` keypads.
+pub fn min_sequence(code: &str, depth: usize) -> String {
+  expand_sequence(code, depth, depth, &Keypad::numeric(), &mut HashMap::new())
+}
 
-    // println!(
-    //   "Code: {code}, Length: {sequence_length}, Numeric: {numeric_part}, Complexity: {complexity}",
-    // ); // for debugging
+/// Buttons a robot arm presses on `keypad` when driven by directional `presses`,
+/// starting on 'A'. `None` if the arm ever points at the gap or off the keypad.
+pub fn type_on(keypad: &Keypad, presses: &str) -> Option<String> {
+  let button_at: HashMap<Position, char> = keypad
+    .buttons
+    .iter()
+    .map(|(&button, &pos)| (pos, button))
+    .collect();
+
+  let (mut row, mut col) = keypad.buttons[&'A'];
+  let mut typed = String::new();
+  for press in presses.chars() {
+    match press {
+      '^' => row -= 1,
+      'v' => row += 1,
+      '<' => col -= 1,
+      '>' => col += 1,
+      'A' => typed.push(button_at[&(row, col)]),
+      _ => return None,
+    }
+    if !button_at.contains_key(&(row, col)) {
+      return None;
+    }
   }
 
-  total_complexity
+  Some(typed)
 }
 
-fn solve(input: &str, part: u8) -> usize {
+/// Sum of `shortest sequence length * numeric part` over the codes, where `depth` counts
+/// the robot arms in the chain: one at the numeric keypad and `depth - 1` at directional
+/// ones, all driven by a human on one more directional keypad. The puzzle uses 3 for
+/// part 1 and 26 for part 2.
+///
+/// ```
+/// use aoc2024::days::day21::sum_complexities_with_depth;
+///
+/// // Depth 1: the human drives the numeric robot directly, "<A^A>^^AvvvA"
+/// assert_eq!(sum_complexities_with_depth(&["029A"], 1).unwrap(), 12 * 29);
+/// assert_eq!(sum_complexities_with_depth(&["029A"], 3).unwrap(), 68 * 29);
+/// assert_eq!(sum_complexities_with_depth(&["029A"], 4).unwrap(), 164 * 29);
+/// ```
+pub fn sum_complexities_with_depth(codes: &[&str], depth: usize) -> Result<usize> {
+  Ok(
+    complexity_by_code(codes, depth)?
+      .iter()
+      .map(|(_, sequence_length, numeric_part)| sequence_length * numeric_part)
+      .sum(),
+  )
+}
+
+/// Each code with its shortest sequence length and numeric part, in input order.
+pub fn complexity_by_code(codes: &[&str], depth: usize) -> Result<Vec<(String, usize, usize)>> {
+  let numeric = Keypad::numeric();
+  let mut memo = HashMap::new();
+
+  codes
+    .iter()
+    .map(|code| {
+      // Check the code before walking the keypads with it
+      let numeric_part = numeric_part(code)?;
+      let sequence_length = min_sequence_length(code, depth, depth, &numeric, &mut memo);
+
+      Ok((code.to_string(), sequence_length, numeric_part))
+    })
+    .collect()
+}
+
+/// The number formed by a code's digits, e.g. 29 for "029A". A code without any digits
+/// has no meaningful complexity, so it is an error rather than silently counting as 0.
+fn numeric_part(code: &str) -> Result<usize> {
+  let digits: String = code.chars().filter(|c| c.is_ascii_digit()).collect();
+  if digits.is_empty() {
+    bail!("code {code:?} has no numeric part");
+  }
+  Ok(digits.parse()?)
+}
+
+pub(crate) fn solve(input: &str, part: u8) -> Result<usize> {
   let depth = match part {
     1 => 3,
     2 => 26,
     _ => panic!("Only part 1 or 2 is possible."),
   };
 
-  let codes: Vec<&str> = input.lines().collect();
+  // Blank lines (e.g. a trailing one) are not codes
+  let codes: Vec<&str> = input
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty())
+    .collect();
 
   sum_complexities_with_depth(&codes, depth)
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
-  println!("Part 1 result = {}", solve(&input, 1));
-  println!("Part 2 result = {}\n", solve(&input, 2));
+  println!("Part 1 result = {}", solve(&input, 1)?);
+  println!("Part 2 result = {}\n", solve(&input, 2)?);
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day21_simple.txt", "Simple puzzle")?;
   print_result("input/day21_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashSet;
+
+  const SAMPLE: &str = include_str!("../input/day21_simple.txt");
+  const SAMPLE_CODES: [&str; 5] = ["029A", "980A", "179A", "456A", "379A"];
+
+  /// Arms after the human presses `key`, with how much of `code` the top arm has typed;
+  /// `None` if an arm leaves its keypad or the top arm presses the wrong button
+  fn press(
+    keypads: &[&Keypad],
+    arms: &[Position],
+    mut key: char,
+    code: &[char],
+    typed: usize,
+  ) -> Option<(Vec<Position>, usize)> {
+    let mut arms = arms.to_vec();
+    for (level, keypad) in keypads.iter().enumerate() {
+      let (row, col) = arms[level];
+      let next = match key {
+        '^' => (row - 1, col),
+        'v' => (row + 1, col),
+        '<' => (row, col - 1),
+        '>' => (row, col + 1),
+        _ => {
+          let (&button, _) = keypad
+            .buttons
+            .iter()
+            .find(|&(_, &pos)| pos == arms[level])?;
+          if level + 1 < keypads.len() {
+            key = button;
+            continue;
+          }
+          return (code.get(typed) == Some(&button)).then_some((arms, typed + 1));
+        }
+      };
+      if !keypad.is_button(next) {
+        return None;
+      }
+      arms[level] = next;
+      break;
+    }
+    Some((arms, typed))
+  }
+
+  /// Fewest human presses that type `code` on `top` through `depth` robot arms, by BFS
+  /// over the positions of every arm at once
+  fn brute_force_presses(top: &Keypad, depth: usize, code: &str) -> Option<usize> {
+    let directional = Keypad::directional();
+    let keypads: Vec<&Keypad> = (1..depth).map(|_| &directional).chain([top]).collect();
+    let code: Vec<char> = code.chars().collect();
+
+    let start: Vec<Position> = keypads.iter().map(|keypad| keypad.buttons[&'A']).collect();
+    let mut seen = HashSet::from([(start.clone(), 0)]);
+    let mut queue = VecDeque::from([(start, 0, 0)]);
+    while let Some((arms, typed, presses)) = queue.pop_front() {
+      if typed == code.len() {
+        return Some(presses);
+      }
+      for key in "^v<>A".chars() {
+        if let Some(state) = press(&keypads, &arms, key, &code, typed)
+          && seen.insert(state.clone())
+        {
+          queue.push_back((state.0, state.1, presses + 1));
+        }
+      }
+    }
+    None
+  }
+
+  #[test]
+  fn parses_keypad_layouts() {
+    let keypad = Keypad::from_layout(&["789", "456", "123", " 0A"]);
+    assert_eq!(keypad.buttons, Keypad::numeric().buttons);
+    assert_eq!(keypad.gap, (3, 0));
+    assert_eq!(keypad.buttons[&'A'], (3, 2));
+
+    let directional = Keypad::directional();
+    assert_eq!(directional.gap, (0, 0));
+    assert_eq!(directional.buttons.len(), 5);
+    assert_eq!(directional.buttons[&'<'], (1, 0));
+
+    // Without a gap nothing is off limits
+    let full = Keypad::from_layout(&["12", "3A"]);
+    assert_eq!(full.gap, (-1, -1));
+    assert!((0..2).all(|r| (0..2).all(|c| full.is_button((r, c)))));
+  }
+
+  #[test]
+  fn get_paths_enumerates_every_interleaving() {
+    let keypad = Keypad::from_layout(&["123", "456", "789"]);
+    let mut paths = keypad.get_paths('1', '9');
+    paths.sort_unstable();
+    assert_eq!(paths, [">>vv", ">v>v", ">vv>", "v>>v", "v>v>", "vv>>"]);
+
+    // 7 8 9
+    // 4 5
+    //   2 3
+    // Both straight routes from 7 to 3 fall off the keypad; only the zigzags remain
+    let keypad = Keypad::from_layout(&["789", "45 ", " 23"]);
+    assert_eq!(keypad.get_paths('7', '3'), ["v>v>", ">vv>"]);
+  }
+
+  #[test]
+  fn zigzag_only_keypad_matches_brute_force() {
+    // Every route between 7 and A has to zigzag between the two gaps...
+    let top = Keypad::from_layout(&["789", "45 ", " 2A"]);
+    assert!(top.get_paths('7', 'A').iter().all(|path| path.len() == 4));
+    assert!(!top.get_paths('7', 'A').contains(&"vv>>".to_string()));
+    // and A to 9 has to step around the gap above A
+    assert_eq!(top.get_paths('A', '9'), ["<^^>"]);
+
+    for code in ["7A", "72A", "97A"] {
+      for depth in 1..=3 {
+        let presses = expand_sequence(code, depth, depth, &top, &mut HashMap::new());
+        let length = min_sequence_length(code, depth, depth, &top, &mut HashMap::new());
+        assert_eq!(presses.len(), length);
+        assert_eq!(
+          Some(length),
+          brute_force_presses(&top, depth, code),
+          "{code} at {depth}"
+        );
+
+        let typed = (1..depth).fold(presses, |presses, _| {
+          type_on(&Keypad::directional(), &presses).unwrap()
+        });
+        assert_eq!(type_on(&top, &typed).as_deref(), Some(code));
+      }
+    }
+  }
+
+  #[test]
+  fn brute_force_agrees_on_the_numeric_keypad() {
+    let numeric = Keypad::numeric();
+    for code in SAMPLE_CODES {
+      assert_eq!(
+        brute_force_presses(&numeric, 3, code),
+        Some(min_sequence_length(
+          code,
+          3,
+          3,
+          &numeric,
+          &mut HashMap::new()
+        ))
+      );
+    }
+  }
+
+  #[test]
+  fn depth_four_small_case() {
+    // By hand: "<A>A" at depth 1, then 4 + 4 + 2 + 2 presses one keypad further down
+    assert_eq!(min_sequence("0A", 1), "<A>A");
+    assert_eq!(min_sequence("0A", 2), "v<<A>^>AvA^A");
+
+    let numeric = Keypad::numeric();
+    let length = brute_force_presses(&numeric, 4, "1A").unwrap();
+    assert_eq!(min_sequence("1A", 4).len(), length);
+    assert_eq!(sum_complexities_with_depth(&["1A"], 4).unwrap(), length);
+    assert_eq!(
+      sum_complexities_with_depth(&["029A"], 4).unwrap(),
+      brute_force_presses(&numeric, 4, "029A").unwrap() * 29
+    );
+  }
+
+  #[test]
+  fn complexity_breakdown() {
+    let breakdown = complexity_by_code(&SAMPLE_CODES, 3).unwrap();
+    let codes: Vec<&str> = breakdown.iter().map(|(code, _, _)| code.as_str()).collect();
+    let lengths: Vec<usize> = breakdown.iter().map(|&(_, len, _)| len).collect();
+    let numbers: Vec<usize> = breakdown.iter().map(|&(_, _, num)| num).collect();
+    assert_eq!(codes, SAMPLE_CODES);
+    assert_eq!(lengths, [68, 60, 68, 64, 64]);
+    assert_eq!(numbers, [29, 980, 179, 456, 379]);
+
+    let total: usize = breakdown.iter().map(|&(_, len, num)| len * num).sum();
+    assert_eq!(
+      total,
+      sum_complexities_with_depth(&SAMPLE_CODES, 3).unwrap()
+    );
+  }
+
+  #[test]
+  fn codes_need_a_numeric_part() {
+    assert_eq!(numeric_part("029A").unwrap(), 29);
+    assert_eq!(numeric_part("000A").unwrap(), 0);
+    let err = numeric_part("XYZA").unwrap_err();
+    assert!(err.to_string().contains("no numeric part"));
+    assert!(sum_complexities_with_depth(&["029A", "XYZA"], 3).is_err());
+  }
+
+  #[test]
+  fn blank_lines_are_not_codes() {
+    let expected = solve(SAMPLE, 1).unwrap();
+    assert_eq!(solve(&format!("{SAMPLE}\n\n"), 1).unwrap(), expected);
+    assert_eq!(
+      solve(&format!("\n{}", SAMPLE.replace('\n', "\r\n")), 1).unwrap(),
+      expected
+    );
+  }
+
+  #[test]
+  fn sample_answers() {
+    assert_eq!(solve(SAMPLE, 1).unwrap(), 126384);
+  }
+
+  #[test]
+  fn min_sequence_types_the_code() {
+    for code in SAMPLE_CODES {
+      let presses = min_sequence(code, 3);
+      assert_eq!(
+        presses.len(),
+        min_sequence_length(code, 3, 3, &Keypad::numeric(), &mut HashMap::new())
+      );
+
+      // Typing it back through the chain gives the code again
+      let robot_1 = type_on(&Keypad::directional(), &presses).unwrap();
+      let robot_2 = type_on(&Keypad::directional(), &robot_1).unwrap();
+      assert_eq!(type_on(&Keypad::numeric(), &robot_2).unwrap(), code);
+    }
+    assert_eq!(min_sequence("029A", 3).len(), 68);
+  }
+
+  #[test]
+  fn type_on_rejects_the_gap() {
+    assert_eq!(type_on(&Keypad::numeric(), "<A").as_deref(), Some("0"));
+    assert_eq!(type_on(&Keypad::numeric(), "<<A"), None);
+    assert_eq!(type_on(&Keypad::directional(), "^<A"), None);
+    assert_eq!(type_on(&Keypad::directional(), "x"), None);
+  }
+}
@@ -1,8 +1,14 @@
 use anyhow::Result;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::ops::{BitAnd, BitOr, BitXor};
 
-#[derive(Debug, Clone, PartialEq)]
+/// A value a wire can carry: the puzzle's 0/1 integers, or plain `bool`s
+trait Signal: Copy + BitAnd<Output = Self> + BitOr<Output = Self> + BitXor<Output = Self> {}
+
+impl<T: Copy + BitAnd<Output = T> + BitOr<Output = T> + BitXor<Output = T>> Signal for T {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Operation {
   And,
   Or,
@@ -19,7 +25,15 @@ impl Operation {
     }
   }
 
-  fn apply(&self, op1: i32, op2: i32) -> i32 {
+  fn as_str(&self) -> &'static str {
+    match self {
+      Operation::And => "AND",
+      Operation::Or => "OR",
+      Operation::Xor => "XOR",
+    }
+  }
+
+  fn apply<T: Signal>(&self, op1: T, op2: T) -> T {
     match self {
       Operation::And => op1 & op2,
       Operation::Or => op1 | op2,
@@ -29,14 +43,14 @@ impl Operation {
 }
 
 #[derive(Debug, Clone)]
-struct GateOperation {
+pub struct GateOperation {
   input1: String,
   input2: String,
   output: String,
   operation: Operation,
 }
 
-fn parse_input(content: &str) -> Result<(HashMap<String, i32>, Vec<GateOperation>), String> {
+pub fn parse_input(content: &str) -> Result<(HashMap<String, i32>, Vec<GateOperation>), String> {
   let mut wires = HashMap::new();
   let mut operations = Vec::new();
 
@@ -83,129 +97,897 @@ fn parse_input(content: &str) -> Result<(HashMap<String, i32>, Vec<GateOperation
   Ok((wires, operations))
 }
 
-fn find_highest_z_wire(operations: &[GateOperation]) -> String {
+/// Number of bits in each of the `x` and `y` operands, i.e. one past the highest `xNN`
+fn input_width(operations: &[GateOperation]) -> usize {
   operations
     .iter()
-    .filter(|op| op.output.starts_with('z'))
-    .map(|op| &op.output)
-    .max_by_key(|wire| wire[1..].parse::<u32>().unwrap_or(0))
-    .unwrap_or(&"z00".to_string())
-    .clone()
-}
-
-fn find_wrong_wires(operations: &[GateOperation]) -> Vec<String> {
-  let mut wrong = Vec::new();
-  let highest_z = find_highest_z_wire(operations);
-
-  for op in operations {
-    // rule 1: z wires (except highest) should use XOR
-    if op.output.starts_with('z') && op.operation != Operation::Xor && op.output != highest_z {
-      wrong.push(op.output.clone());
-    }
-
-    // rule 2: XOR between non-input wires shouldn't exist in certain contexts
-    if op.operation == Operation::Xor
-      && !op.output.starts_with('x')
-      && !op.output.starts_with('y')
-      && !op.output.starts_with('z')
-      && !op.input1.starts_with('x')
-      && !op.input1.starts_with('y')
-      && !op.input1.starts_with('z')
-      && !op.input2.starts_with('x')
-      && !op.input2.starts_with('y')
-      && !op.input2.starts_with('z')
-    {
-      wrong.push(op.output.clone());
-    }
-
-    // rule 3: AND operations (except x00) should feed into OR operations
-    if op.operation == Operation::And && op.input1 != "x00" && op.input2 != "x00" {
-      for sub_op in operations {
-        if (op.output == sub_op.input1 || op.output == sub_op.input2)
-          && sub_op.operation != Operation::Or
-        {
-          wrong.push(op.output.clone());
-          break;
-        }
+    .flat_map(|op| [&op.input1, &op.input2])
+    .filter(|wire| wire.starts_with('x'))
+    .filter_map(|wire| wire[1..].parse::<usize>().ok())
+    .max()
+    .map_or(0, |bit| bit + 1)
+}
+
+/// A gate over numbered wires
+#[derive(Debug, Clone)]
+struct Gate {
+  inputs: [usize; 2],
+  operation: Operation,
+  output: usize,
+}
+
+/// An adder-shaped circuit with its wires numbered and its gates in input order, cheap
+/// enough to re-simulate after every swap `find_swaps` tries
+#[derive(Debug, Clone)]
+struct Netlist {
+  gates: Vec<Gate>,
+  /// The gates reading each wire, once per input it feeds. Swaps only move outputs, so
+  /// this never changes.
+  readers: Vec<Vec<usize>>,
+  /// The `xNN` and `yNN` wires, by bit
+  x: Vec<usize>,
+  y: Vec<usize>,
+  /// The `zNN` wires, by bit: one more than `x` for the carry
+  z: Vec<usize>,
+}
+
+impl Netlist {
+  /// Fails if no swapping of outputs could make this an adder: the operands don't fit in
+  /// a `u64`, the z wires aren't exactly `z00` up to one past the operand width, or a
+  /// gate reads a wire that is neither an operand bit nor driven by a gate
+  fn new(operations: &[GateOperation]) -> Result<Self, String> {
+    let width = input_width(operations);
+    if width == 0 || width > 63 {
+      return Err(format!("can't check a {width}-bit adder"));
+    }
+
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    let mut id = |wire: &str| {
+      let next = ids.len();
+      *ids.entry(wire.to_string()).or_insert(next)
+    };
+    let x: Vec<usize> = (0..width).map(|bit| id(&format!("x{bit:02}"))).collect();
+    let y: Vec<usize> = (0..width).map(|bit| id(&format!("y{bit:02}"))).collect();
+    let gates: Vec<Gate> = operations
+      .iter()
+      .map(|op| Gate {
+        inputs: [id(&op.input1), id(&op.input2)],
+        operation: op.operation,
+        output: id(&op.output),
+      })
+      .collect();
+
+    let mut readers = vec![Vec::new(); ids.len()];
+    let mut driven = vec![false; ids.len()];
+    for &wire in x.iter().chain(&y) {
+      driven[wire] = true;
+    }
+    for (gate, Gate { inputs, output, .. }) in gates.iter().enumerate() {
+      for &input in inputs {
+        readers[input].push(gate);
       }
+      driven[*output] = true;
+    }
+    if let Some((wire, _)) = ids.iter().find(|&(_, &id)| !driven[id]) {
+      return Err(format!("wire {wire} has no value and no gate drives it"));
     }
 
-    // rule 4: XOR operations shouldn't feed into OR operations
-    if op.operation == Operation::Xor {
-      for sub_op in operations {
-        if (op.output == sub_op.input1 || op.output == sub_op.input2)
-          && sub_op.operation == Operation::Or
-        {
-          wrong.push(op.output.clone());
-          break;
+    let mut z: Vec<(usize, usize)> = ids
+      .iter()
+      .filter_map(|(wire, &id)| Some((wire.strip_prefix('z')?.parse().ok()?, id)))
+      .collect();
+    z.sort_unstable();
+    // Swaps only move outputs around, so the z wires must already be the ones a sum needs
+    if !z.iter().map(|&(bit, _)| bit).eq(0..=width) {
+      return Err(format!(
+        "not an adder: {width}-bit operands need z00 to z{width:02}, but there are {} z wires",
+        z.len()
+      ));
+    }
+
+    Ok(Self {
+      gates,
+      readers,
+      x,
+      y,
+      z: z.into_iter().map(|(_, id)| id).collect(),
+    })
+  }
+
+  fn width(&self) -> usize {
+    self.x.len()
+  }
+
+  /// Exchange the outputs of two gates; doing it again swaps them back
+  fn swap_outputs(&mut self, a: usize, b: usize) {
+    let output = self.gates[a].output;
+    self.gates[a].output = self.gates[b].output;
+    self.gates[b].output = output;
+  }
+
+  /// Run every gate it can, like `propagate`, with `x` and `y` giving each operand bit's
+  /// lanes. Returns each wire's lanes and the gates a cycle kept from running.
+  fn run(&self, x: &[u64], y: &[u64]) -> (Vec<u64>, Vec<usize>) {
+    let mut values = vec![0u64; self.readers.len()];
+    let mut missing = vec![2u8; self.gates.len()];
+    let mut ready = Vec::new();
+    // A gate runs once both of its inputs are known
+    let release = |wire: usize, missing: &mut [u8], ready: &mut Vec<usize>| {
+      for &reader in &self.readers[wire] {
+        missing[reader] -= 1;
+        if missing[reader] == 0 {
+          ready.push(reader);
         }
       }
+    };
+
+    for (&wire, &lanes) in self.x.iter().zip(x).chain(self.y.iter().zip(y)) {
+      values[wire] = lanes;
+      release(wire, &mut missing, &mut ready);
+    }
+    while let Some(gate) = ready.pop() {
+      let Gate {
+        inputs: [a, b],
+        operation,
+        output,
+      } = self.gates[gate];
+      values[output] = operation.apply(values[a], values[b]);
+      release(output, &mut missing, &mut ready);
     }
+
+    let stuck = (0..self.gates.len())
+      .filter(|&gate| missing[gate] > 0)
+      .collect();
+    (values, stuck)
   }
 
-  wrong.sort();
-  wrong.dedup();
-  wrong
-}
+  /// Gates that can never run because they wait on each other
+  fn stuck_on_cycle(&self) -> Vec<usize> {
+    let zeros = vec![0; self.width()];
+    self.run(&zeros, &zeros).1
+  }
 
-fn simulate_circuit(
-  mut wires: HashMap<String, i32>,
-  operations: Vec<GateOperation>,
-) -> HashMap<String, i32> {
-  let mut queue: VecDeque<GateOperation> = operations.into();
+  /// Lowest z bit that comes out wrong on any of `additions`, or `None` if the circuit
+  /// gets them all right. Gates caught in a cycle just don't run, so a bad swap can still
+  /// be narrowed down to the bits it breaks.
+  fn first_wrong_bit(&self, additions: &[Additions]) -> Option<usize> {
+    let mut lowest: Option<usize> = None;
+    for additions in additions {
+      let (values, stuck) = self.run(&additions.x, &additions.y);
 
-  while let Some(op) = queue.pop_front() {
-    if let (Some(&val1), Some(&val2)) = (wires.get(&op.input1), wires.get(&op.input2)) {
-      let result = op.operation.apply(val1, val2);
-      wires.insert(op.output, result);
-    } else {
-      // inputs not ready yet, put back at end of queue
-      queue.push_back(op);
+      // A z wire stuck on a cycle is as wrong as a z wire can be
+      let stuck_bits = stuck
+        .iter()
+        .filter_map(|&gate| self.z.iter().position(|&z| z == self.gates[gate].output));
+      let wrong_bit = (self.z.iter().zip(&additions.sum)).position(|(&z, &sum)| values[z] != sum);
+      lowest = stuck_bits.chain(wrong_bit).chain(lowest).min();
     }
+
+    lowest
   }
 
-  wires
+  /// Gates that take part in adding `bit`: everything within two gates of the `x`/`y`
+  /// wires at this bit and the one below (which makes the carry in), plus whatever
+  /// drives the `z` wire at `bit`
+  fn gates_near_bit(&self, bit: usize) -> Vec<usize> {
+    let mut wires: Vec<usize> = (bit.saturating_sub(1)..=bit.min(self.width() - 1))
+      .flat_map(|bit| [self.x[bit], self.y[bit]])
+      .collect();
+    let mut near = Vec::new();
+
+    for _hop in 0..2 {
+      let reached: Vec<usize> = wires
+        .iter()
+        .flat_map(|&wire| self.readers[wire].iter().copied())
+        .collect();
+      wires = reached
+        .iter()
+        .map(|&gate| self.gates[gate].output)
+        .collect();
+      near.extend(reached);
+    }
+    if let Some(&z) = self.z.get(bit) {
+      near.extend((0..self.gates.len()).filter(|&gate| self.gates[gate].output == z));
+    }
+
+    near.sort_unstable();
+    near.dedup();
+    near
+  }
 }
 
-fn calculate_z_output(wires: &HashMap<String, i32>) -> u64 {
-  let mut z_wires: Vec<_> = wires
+/// Up to 64 additions run at once, one per bit lane of a `u64` wire value: the lanes of
+/// each operand bit and of each bit of the expected sum
+struct Additions {
+  x: Vec<u64>,
+  y: Vec<u64>,
+  sum: Vec<u64>,
+}
+
+impl Additions {
+  /// `checks` as `(x, y)` operands of a `width`-bit adder, 64 to a batch
+  fn batches(checks: &[(u64, u64)], width: usize) -> Vec<Self> {
+    checks
+      .chunks(64)
+      .map(|chunk| {
+        let lanes = |bits: usize, operand: &dyn Fn(u64, u64) -> u64| -> Vec<u64> {
+          (0..bits)
+            .map(|bit| {
+              chunk.iter().enumerate().fold(0, |lanes, (lane, &(x, y))| {
+                lanes | ((operand(x, y) >> bit) & 1) << lane
+              })
+            })
+            .collect()
+        };
+        Self {
+          x: lanes(width, &|x, _| x),
+          y: lanes(width, &|_, y| y),
+          sum: lanes(width + 1, &|x, y| x + y),
+        }
+      })
+      .collect()
+  }
+}
+
+/// xorshift64: plenty to pick varied test additions without a rand dependency
+struct XorShift(u64);
+
+impl XorShift {
+  fn next(&mut self) -> u64 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    self.0
+  }
+}
+
+/// Random additions checked against the circuit on top of a carry into and out of
+/// every bit, which the random ones alone could miss
+const RANDOM_ADDITIONS: usize = 64;
+
+/// The `(x, y)` operands a `width`-bit adder is checked on
+fn adder_checks(width: usize) -> Vec<(u64, u64)> {
+  let mask = (1u64 << width) - 1;
+  let mut rng = XorShift(0x2024_1224);
+  let bitwise = (0..width).flat_map(|bit| [(1 << bit, 0), (0, 1 << bit), (1 << bit, 1 << bit)]);
+  let random = (0..RANDOM_ADDITIONS).map(|_| (rng.next() & mask, rng.next() & mask));
+
+  bitwise
+    .chain(random)
+    .chain([(mask, 1), (mask, mask)])
+    .collect()
+}
+
+/// Gate outputs whose role doesn't fit a ripple-carry adder, where for each bit i > 0
+///
+/// ```text
+/// a_i = x_i XOR y_i      z_i = a_i XOR c_(i-1)
+/// b_i = x_i AND y_i      d_i = a_i AND c_(i-1)
+/// c_i = b_i OR d_i
+/// ```
+///
+/// and bit 0 is a half adder (`z00 = x00 XOR y00`, `c_0 = x00 AND y00`) with the last
+/// carry landing on the top z wire. Each gate is judged by what it reads and what reads
+/// it, so a swapped output shows up on both of the gates involved. `find_swaps` doesn't
+/// rely on this, since a correct adder laid out differently would trip it.
+///
+/// ```
+/// use aoc2024::days::day24::{parse_input, suspicious_wires};
+///
+/// // A 2-bit adder with the outputs of bit 1's XOR and AND of the operands swapped
+/// let (_, operations) = parse_input(
+///   "x00 XOR y00 -> z00\nx00 AND y00 -> c00\n\
+///    x01 XOR y01 -> b01\nx01 AND y01 -> a01\n\
+///    a01 XOR c00 -> z01\na01 AND c00 -> d01\nb01 OR d01 -> z02",
+/// )
+/// .unwrap();
+/// assert_eq!(suspicious_wires(&operations), ["a01", "b01"]);
+/// ```
+pub fn suspicious_wires(operations: &[GateOperation]) -> Vec<String> {
+  let top_z = format!("z{:02}", input_width(operations));
+  let is_input = |wire: &str| wire.starts_with('x') || wire.starts_with('y');
+  let feeds = |wire: &str, operation: Operation| {
+    operations
+      .iter()
+      .any(|op| op.operation == operation && (op.input1 == wire || op.input2 == wire))
+  };
+  // A carry is always read by the next bit's sum XOR and carry AND
+  let is_carry =
+    |wire: &str| wire == top_z || (feeds(wire, Operation::Xor) && feeds(wire, Operation::And));
+
+  let mut suspects: Vec<String> = operations
     .iter()
-    .filter(|(wire, _)| wire.starts_with('z'))
+    .filter(|op| {
+      let from_inputs = is_input(&op.input1) && is_input(&op.input2);
+      let first_bit = from_inputs && op.input1.ends_with("00");
+      let out = op.output.as_str();
+      let consistent = match op.operation {
+        Operation::Xor if first_bit => out == "z00",
+        Operation::Xor if from_inputs => feeds(out, Operation::Xor) && feeds(out, Operation::And),
+        Operation::Xor => out.starts_with('z') && out != top_z,
+        Operation::And if first_bit => is_carry(out),
+        Operation::And => feeds(out, Operation::Or),
+        Operation::Or => is_carry(out),
+      };
+      !consistent
+    })
+    .map(|op| op.output.clone())
+    .collect();
+
+  suspects.sort();
+  suspects.dedup();
+  suspects
+}
+
+/// The puzzle swaps this many pairs of gate outputs
+const SWAPPED_PAIRS: usize = 4;
+
+/// The fewest output swaps that turn the circuit into a working adder, as sorted pairs.
+/// Fails if no swapping can make the circuit an adder (see `Netlist::new`), e.g. for the
+/// part 2 sample (`z = x AND y`), or if nothing within `SWAPPED_PAIRS` swaps does.
+///
+/// Swaps are searched bit by bit with iterative deepening: find the lowest bit that
+/// adds wrong, try swapping each gate near that bit (or stuck on a cycle) with the gates
+/// near it and the next bit up, and keep a swap only if the lowest wrong bit moves up.
+/// Deepening one swap at a time means the first fix found is a smallest one, as long as
+/// each swapped pair sits within a bit of each other, as in the puzzle. Candidates are
+/// judged by simulating `adder_checks`, so an unusual but correct layout still passes.
+///
+/// ```
+/// use aoc2024::days::day24::{find_swaps, parse_input};
+///
+/// let (_, operations) = parse_input(
+///   "x00 XOR y00 -> z00\nx00 AND y00 -> c00\n\
+///    x01 XOR y01 -> b01\nx01 AND y01 -> a01\n\
+///    a01 XOR c00 -> z01\na01 AND c00 -> d01\nb01 OR d01 -> z02",
+/// )
+/// .unwrap();
+/// assert_eq!(find_swaps(&operations), Ok(vec![("a01".into(), "b01".into())]));
+/// ```
+pub fn find_swaps(operations: &[GateOperation]) -> Result<Vec<(String, String)>, String> {
+  let mut netlist = Netlist::new(operations)?;
+  let checks = Additions::batches(&adder_checks(netlist.width()), netlist.width());
+
+  let swaps = (0..=SWAPPED_PAIRS)
+    .find_map(|budget| {
+      let mut swaps = Vec::new();
+      repair(&mut netlist, &checks, budget, &mut swaps).then_some(swaps)
+    })
+    .ok_or_else(|| format!("no {SWAPPED_PAIRS} output swaps turn the circuit into an adder"))?;
+
+  let mut swaps: Vec<(String, String)> = swaps
+    .into_iter()
+    .map(|(a, b)| {
+      let (a, b) = (&operations[a].output, &operations[b].output);
+      (a.min(b).clone(), a.max(b).clone())
+    })
+    .collect();
+  swaps.sort();
+  Ok(swaps)
+}
+
+/// Fix the lowest bit that gets `checks` wrong with one swap and recurse, using at most
+/// `budget` more swaps. `swaps` collects the swapped gates; on success they are left
+/// swapped in `netlist`.
+fn repair(
+  netlist: &mut Netlist,
+  checks: &[Additions],
+  budget: usize,
+  swaps: &mut Vec<(usize, usize)>,
+) -> bool {
+  let Some(bit) = netlist.first_wrong_bit(checks) else {
+    return true;
+  };
+  if budget == 0 {
+    return false;
+  }
+
+  // One batch of random operands that only use the bits up to `bit`
+  let width = netlist.width();
+  let low_mask = (2u64 << bit.min(width - 1)) - 1;
+  let mut rng = XorShift(0x2024_1224);
+  let low_checks: Vec<(u64, u64)> = (0..64)
+    .map(|_| (rng.next() & low_mask, rng.next() & low_mask))
+    .collect();
+  let low_checks = Additions::batches(&low_checks, width);
+
+  // The gate with the wrong output sits at `bit`, but its partner may belong one higher
+  // A gate wired into its own inputs is wrong wherever it sits
+  let mut suspects = netlist.gates_near_bit(bit);
+  suspects.extend(netlist.stuck_on_cycle());
+  suspects.sort_unstable();
+  suspects.dedup();
+  let mut partners = suspects.clone();
+  partners.extend(netlist.gates_near_bit(bit + 1));
+  partners.sort_unstable();
+  partners.dedup();
+
+  // Only worth pursuing if everything up to and including `bit` now adds right: a quick
+  // screen on small operands first, then the full checks
+  let fixed = |netlist: &Netlist, checks: &[Additions]| {
+    netlist
+      .first_wrong_bit(checks)
+      .is_none_or(|next| next > bit)
+  };
+
+  for &near in &suspects {
+    for &other in &partners {
+      if other == near || swaps.iter().any(|&(a, b)| a == other || b == other) {
+        continue;
+      }
+
+      netlist.swap_outputs(near, other);
+      if fixed(netlist, &low_checks) && fixed(netlist, checks) {
+        swaps.push((near.min(other), near.max(other)));
+        if repair(netlist, checks, budget - 1, swaps) {
+          return true;
+        }
+        swaps.pop();
+      }
+      netlist.swap_outputs(near, other);
+    }
+  }
+
+  false
+}
+
+/// Wire names sorted and comma-joined, the form part 2 wants
+fn sorted_wire_list(mut wires: Vec<String>) -> String {
+  wires.sort();
+  wires.join(",")
+}
+
+/// Evaluate every gate in dependency order (Kahn's algorithm): a gate runs once both of
+/// its inputs are known, then releases the gates reading its output. Gates left over at
+/// the end are waiting on each other, i.e. they form a cycle.
+fn simulate_circuit<T: Signal>(
+  wires: HashMap<String, T>,
+  operations: &[GateOperation],
+) -> Result<HashMap<String, T>, String> {
+  let (wires, stuck) = propagate(wires, operations)?;
+  if !stuck.is_empty() {
+    return Err(format!(
+      "gates driving {} are stuck on a cycle",
+      stuck.join(",")
+    ));
+  }
+
+  Ok(wires)
+}
+
+/// Evaluate whatever gates `simulate_circuit` can, returning the wires along with the
+/// outputs of the gates a cycle kept from running
+fn propagate<T: Signal>(
+  mut wires: HashMap<String, T>,
+  operations: &[GateOperation],
+) -> Result<(HashMap<String, T>, Vec<String>), String> {
+  let produced: HashSet<&str> = operations.iter().map(|op| op.output.as_str()).collect();
+
+  // Unknown inputs per gate, and the gates reading each wire (once per input it feeds)
+  let mut missing = vec![0; operations.len()];
+  let mut readers: HashMap<&str, Vec<usize>> = HashMap::new();
+  for (gate, op) in operations.iter().enumerate() {
+    for input in [&op.input1, &op.input2] {
+      if wires.contains_key(input) {
+        continue;
+      }
+      if !produced.contains(input.as_str()) {
+        return Err(format!("wire {input} has no value and no gate drives it"));
+      }
+      missing[gate] += 1;
+      readers.entry(input).or_default().push(gate);
+    }
+  }
+
+  let mut ready: Vec<usize> = (0..operations.len())
+    .filter(|&gate| missing[gate] == 0)
+    .collect();
+  while let Some(gate) = ready.pop() {
+    let op = &operations[gate];
+    let result = op.operation.apply(wires[&op.input1], wires[&op.input2]);
+    wires.insert(op.output.clone(), result);
+
+    for &reader in readers.get(op.output.as_str()).into_iter().flatten() {
+      missing[reader] -= 1;
+      if missing[reader] == 0 {
+        ready.push(reader);
+      }
+    }
+  }
+
+  let stuck = (0..operations.len())
+    .filter(|&gate| missing[gate] > 0)
+    .map(|gate| operations[gate].output.clone())
     .collect();
 
-  z_wires.sort_by_key(|(wire, _)| wire.as_str());
-  z_wires.reverse();
+  Ok((wires, stuck))
+}
 
-  let binary_string: String = z_wires
+/// The z wires read as a sum: `value` from every z wire below the top one, and `carry`
+/// from the top z wire, which an adder only sets when the sum overflows the lower ones
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ZOutput {
+  value: u64,
+  carry: bool,
+  /// How many z wires there are, counting the carry
+  width: usize,
+}
+
+impl ZOutput {
+  /// Fails if there are more z wires than `value` and `carry` can hold
+  fn from_wires(wires: &HashMap<String, bool>) -> Result<Self, String> {
+    let mut z_wires: Vec<_> = wires
+      .iter()
+      .filter(|(wire, _)| wire.starts_with('z'))
+      .collect();
+    z_wires.sort_by_key(|(wire, _)| wire.as_str());
+
+    let Some((&(_, &carry), low)) = z_wires.split_last() else {
+      return Ok(Self {
+        value: 0,
+        carry: false,
+        width: 0,
+      });
+    };
+    if low.len() > 64 {
+      return Err(format!(
+        "{} z wires don't fit in a u64 plus a carry",
+        z_wires.len()
+      ));
+    }
+    let value = low
+      .iter()
+      .enumerate()
+      .fold(0, |value, (bit, &(_, &on))| value | (on as u64) << bit);
+
+    Ok(Self {
+      value,
+      carry,
+      width: z_wires.len(),
+    })
+  }
+
+  /// All z wires as one number; a `u128`, since the carry on top of a full 64-bit
+  /// `value` lands on bit 64
+  fn total(&self) -> u128 {
+    match self.width {
+      0 => 0,
+      width => self.value as u128 | (self.carry as u128) << (width - 1),
+    }
+  }
+}
+
+/// Run the circuit on `bool` wires and read z back with its carry kept apart.
+fn simulate_circuit_bool(
+  wires: HashMap<String, bool>,
+  operations: &[GateOperation],
+) -> Result<ZOutput, String> {
+  ZOutput::from_wires(&simulate_circuit(wires, operations)?)
+}
+
+/// Run the circuit on `x` and `y` instead of the wire values from the input, and read
+/// back z. Each operand fills `x00..`/`y00..` up to the circuit's width, with zeros for
+/// missing high bits; bits beyond the width have no wire and are ignored. Fails on a
+/// cycle, an undriven wire, or a z output too wide for a `u64`.
+///
+/// ```
+/// use aoc2024::days::day24::{evaluate, parse_input};
+///
+/// // A 1-bit half adder
+/// let (_, operations) = parse_input("x00 XOR y00 -> z00\nx00 AND y00 -> z01").unwrap();
+/// assert_eq!(evaluate(&operations, 1, 1), Ok(2));
+/// assert_eq!(evaluate(&operations, 0b11, 0), Ok(1));
+/// ```
+pub fn evaluate(operations: &[GateOperation], x: u64, y: u64) -> Result<u64, String> {
+  let mut wires = HashMap::new();
+  for bit in 0..input_width(operations).min(64) {
+    wires.insert(format!("x{bit:02}"), (x >> bit) & 1 == 1);
+    wires.insert(format!("y{bit:02}"), (y >> bit) & 1 == 1);
+  }
+
+  let total = simulate_circuit_bool(wires, operations)?.total();
+  u64::try_from(total).map_err(|_| format!("z output {total} doesn't fit in a u64"))
+}
+
+/// Graphviz DOT of the circuit, one node per gate named after its output wire and
+/// labeled with its operation, with an edge from each wire the gate reads. Input wires
+/// are `invhouse` nodes and gates driving a z wire get a double border.
+pub fn circuit_to_dot(operations: &[GateOperation]) -> String {
+  let mut gates: Vec<&GateOperation> = operations.iter().collect();
+  gates.sort_by_key(|op| &op.output);
+
+  let produced: HashSet<&str> = gates.iter().map(|op| op.output.as_str()).collect();
+  let mut inputs: Vec<&str> = gates
     .iter()
-    .map(|&(_, &value)| value.to_string())
+    .flat_map(|op| [op.input1.as_str(), op.input2.as_str()])
+    .filter(|wire| !produced.contains(wire))
     .collect();
+  inputs.sort();
+  inputs.dedup();
 
-  u64::from_str_radix(&binary_string, 2).unwrap_or(0)
+  let mut dot = String::from("digraph {\n");
+  for wire in inputs {
+    dot.push_str(&format!("  {wire} [shape=invhouse];\n"));
+  }
+  for op in &gates {
+    let border = if op.output.starts_with('z') {
+      ", peripheries=2"
+    } else {
+      ""
+    };
+    dot.push_str(&format!(
+      "  {} [label=\"{}\\n{}\", shape=box{border}];\n",
+      op.output,
+      op.operation.as_str(),
+      op.output
+    ));
+  }
+  for op in &gates {
+    for input in [&op.input1, &op.input2] {
+      dot.push_str(&format!("  {input} -> {};\n", op.output));
+    }
+  }
+  dot.push_str("}\n");
+  dot
 }
 
-fn solve(input: &str, part: u8) -> String {
-  let (wires, operations) = parse_input(input).expect("Parsing failed.");
+pub(crate) fn solve(input: &str, part: u8) -> Result<String> {
+  let (wires, operations) = parse_input(input).map_err(anyhow::Error::msg)?;
   match part {
     1 => {
-      let final_wires = simulate_circuit(wires, operations);
-      calculate_z_output(&final_wires).to_string()
+      let wires = wires
+        .into_iter()
+        .map(|(wire, value)| (wire, value != 0))
+        .collect();
+      let z = simulate_circuit_bool(wires, &operations).map_err(anyhow::Error::msg)?;
+      Ok(z.total().to_string())
+    }
+    2 => {
+      let swaps = find_swaps(&operations).map_err(anyhow::Error::msg)?;
+      Ok(sorted_wire_list(
+        swaps.into_iter().flat_map(|(a, b)| [a, b]).collect(),
+      ))
     }
-    2 => find_wrong_wires(&operations).join(","),
     _ => panic!("Only part 1 or 2 is possible."),
   }
 }
 
-fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
+pub fn print_result(filepath: &str, puzzle_kind: &str) -> Result<()> {
   let input = fs::read_to_string(filepath)?;
   println!("Input: {puzzle_kind}");
-  println!("Part 1 result = {}", solve(&input, 1));
-  println!("Part 2 result = {}\n", solve(&input, 2));
+  println!("Part 1 result = {}", solve(&input, 1)?);
+  // The part 1 sample isn't an adder, so it has no part 2 answer
+  match solve(&input, 2) {
+    Ok(wires) => println!("Part 2 result = {wires}\n"),
+    Err(err) => println!("Part 2 result = none ({err})\n"),
+  }
   Ok(())
 }
 
-fn main() -> Result<()> {
+pub fn main() -> Result<()> {
   print_result("input/day24_simple.txt", "Simple puzzle")?;
   print_result("input/day24_full.txt", "Full puzzle")?;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE: &str = include_str!("../input/day24_simple.txt");
+
+  #[test]
+  fn sample_part1() {
+    assert_eq!(solve(SAMPLE, 1).unwrap(), "2024");
+  }
+
+  /// `width` z wires as `bool`s, with only `bits` set
+  fn z_wires(width: usize, bits: &[usize]) -> HashMap<String, bool> {
+    (0..width)
+      .map(|bit| (format!("z{bit:02}"), bits.contains(&bit)))
+      .collect()
+  }
+
+  #[test]
+  fn bool_simulation_keeps_the_carry_apart() {
+    // A 2-bit adder: 3 + 1 leaves z01 z00 = 00 and carries into z02
+    let (wires, operations) = parse_input(
+      "x00: 1\nx01: 1\ny00: 1\ny01: 0\n\
+       x00 XOR y00 -> z00\nx00 AND y00 -> c00\n\
+       x01 XOR y01 -> a01\nx01 AND y01 -> b01\n\
+       a01 XOR c00 -> z01\na01 AND c00 -> d01\nb01 OR d01 -> z02",
+    )
+    .unwrap();
+    let wires = wires
+      .into_iter()
+      .map(|(wire, value)| (wire, value != 0))
+      .collect();
+    let z = simulate_circuit_bool(wires, &operations).unwrap();
+    assert_eq!((z.value, z.carry, z.total()), (0, true, 4));
+  }
+
+  #[test]
+  fn wide_outputs_carry_past_64_bits() {
+    let z = ZOutput::from_wires(&z_wires(65, &[0, 64])).unwrap();
+    assert_eq!((z.value, z.carry), (1, true));
+    assert_eq!(z.total(), 1 << 64 | 1);
+
+    let z = ZOutput::from_wires(&z_wires(64, &[63])).unwrap();
+    assert_eq!((z.value, z.carry, z.total()), (0, true, 1 << 63));
+
+    assert!(ZOutput::from_wires(&z_wires(66, &[])).is_err());
+    assert_eq!(ZOutput::from_wires(&HashMap::new()).unwrap().total(), 0);
+  }
+
+  /// Gates of a `width`-bit ripple-carry adder, with each pair in `swaps` exchanged
+  fn ripple_carry_adder(width: usize, swaps: &[(&str, &str)]) -> String {
+    let mut gates = vec![
+      "x00 XOR y00 -> z00".to_string(),
+      "x00 AND y00 -> c00".to_string(),
+    ];
+    for bit in 1..width {
+      let carry_in = format!("c{:02}", bit - 1);
+      let carry_out = if bit == width - 1 {
+        format!("z{width:02}")
+      } else {
+        format!("c{bit:02}")
+      };
+      gates.extend([
+        format!("x{bit:02} XOR y{bit:02} -> a{bit:02}"),
+        format!("x{bit:02} AND y{bit:02} -> b{bit:02}"),
+        format!("a{bit:02} XOR {carry_in} -> z{bit:02}"),
+        format!("a{bit:02} AND {carry_in} -> d{bit:02}"),
+        format!("b{bit:02} OR d{bit:02} -> {carry_out}"),
+      ]);
+    }
+
+    let swapped: Vec<String> = gates
+      .iter()
+      .map(|gate| {
+        let (inputs, output) = gate.split_once(" -> ").unwrap();
+        let output = swaps
+          .iter()
+          .find_map(|&(a, b)| (output == a).then_some(b).or((output == b).then_some(a)))
+          .unwrap_or(output);
+        format!("{inputs} -> {output}")
+      })
+      .collect();
+    swapped.join("\n")
+  }
+
+  #[test]
+  fn evaluates_any_operands() {
+    let (_, operations) = parse_input(&ripple_carry_adder(12, &[])).unwrap();
+    let mask = (1 << 12) - 1;
+    for (x, y) in [
+      (0, 0),
+      (1, 1),
+      (1234, 2345),
+      (mask, 1),
+      (mask, mask),
+      (2048, 2048),
+    ] {
+      assert_eq!(evaluate(&operations, x, y), Ok(x + y), "{x} + {y}");
+    }
+    // Bits past the 12-bit operands have no wire
+    assert_eq!(evaluate(&operations, 1 << 12 | 5, 3), Ok(8));
+
+    // A swap breaks some sums and a cycle breaks evaluation altogether
+    let (_, operations) = parse_input(&ripple_carry_adder(12, &[("a03", "b03")])).unwrap();
+    assert_ne!(evaluate(&operations, 0b1000, 0), Ok(0b1000));
+    let (_, operations) = parse_input(&ripple_carry_adder(6, &[("c03", "z04")])).unwrap();
+    assert!(evaluate(&operations, 1, 1).is_err());
+  }
+
+  #[test]
+  fn finds_the_swapped_outputs() {
+    let swaps = [("a03", "b03"), ("c05", "z05"), ("d08", "z08")];
+    let input = ripple_carry_adder(12, &swaps);
+    assert_eq!(solve(&input, 2).unwrap(), "a03,b03,c05,d08,z05,z08");
+
+    let (_, operations) = parse_input(&input).unwrap();
+    let found = find_swaps(&operations).unwrap();
+    let expected: Vec<(String, String)> = swaps
+      .iter()
+      .map(|&(a, b)| (a.to_string(), b.to_string()))
+      .collect();
+    assert_eq!(found, expected);
+
+    let (_, operations) = parse_input(&ripple_carry_adder(12, &[])).unwrap();
+    assert!(find_swaps(&operations).unwrap().is_empty());
+  }
+
+  #[test]
+  fn four_swaps_are_found_and_flagged() {
+    let swaps = [
+      ("a03", "b03"),
+      ("c05", "z05"),
+      ("d08", "z08"),
+      ("a10", "d10"),
+    ];
+    let input = ripple_carry_adder(12, &swaps);
+    assert_eq!(solve(&input, 2).unwrap(), "a03,a10,b03,c05,d08,d10,z05,z08");
+
+    let (_, operations) = parse_input(&input).unwrap();
+    assert_eq!(
+      suspicious_wires(&operations),
+      ["a03", "a10", "b03", "c05", "d08", "d10", "z05", "z08"]
+    );
+    let (_, operations) = parse_input(&ripple_carry_adder(12, &[])).unwrap();
+    assert!(suspicious_wires(&operations).is_empty());
+  }
+
+  #[test]
+  fn a_swap_into_a_cycle_is_found() {
+    // The gate driving z04 reads c03, so handing it c03 as its output feeds it its own result
+    let input = ripple_carry_adder(6, &[("c03", "z04")]);
+    let (_, operations) = parse_input(&input).unwrap();
+    assert!(
+      !Netlist::new(&operations)
+        .unwrap()
+        .stuck_on_cycle()
+        .is_empty()
+    );
+    assert_eq!(solve(&input, 2).unwrap(), "c03,z04");
+  }
+
+  #[test]
+  fn circuits_that_cant_be_adders_are_errors() {
+    // The part 2 sample: z = x AND y, one z wire short of a sum
+    let and_gates = "x00 AND y00 -> z05\nx01 AND y01 -> z02\nx02 AND y02 -> z01\n\
+                     x03 AND y03 -> z03\nx04 AND y04 -> z04\nx05 AND y05 -> z00";
+    let err = solve(and_gates, 2).unwrap_err();
+    assert!(err.to_string().starts_with("not an adder"), "{err}");
+    assert!(solve(SAMPLE, 2).is_err());
+
+    let too_many = [
+      ("a02", "b02"),
+      ("a04", "b04"),
+      ("a06", "b06"),
+      ("a08", "b08"),
+      ("a10", "b10"),
+    ];
+    let err = solve(&ripple_carry_adder(12, &too_many), 2).unwrap_err();
+    assert!(err.to_string().starts_with("no 4 output swaps"), "{err}");
+  }
+
+  #[test]
+  fn dot_has_a_node_per_gate_and_an_edge_per_input() {
+    let (_, operations) = parse_input("x00 AND y00 -> a\na XOR x00 -> z00").unwrap();
+    assert_eq!(
+      circuit_to_dot(&operations),
+      "digraph {\n  \
+         x00 [shape=invhouse];\n  \
+         y00 [shape=invhouse];\n  \
+         a [label=\"AND\\na\", shape=box];\n  \
+         z00 [label=\"XOR\\nz00\", shape=box, peripheries=2];\n  \
+         x00 -> a;\n  \
+         y00 -> a;\n  \
+         a -> z00;\n  \
+         x00 -> z00;\n\
+       }\n"
+    );
+  }
+
+  #[test]
+  fn gates_run_in_dependency_order() {
+    // Listed backwards, so each gate reads a wire that a later line produces
+    let (wires, operations) =
+      parse_input("x00: 1\ny00: 0\nb XOR x00 -> z00\nx00 OR y00 -> b").unwrap();
+    let wires = simulate_circuit(wires, &operations).unwrap();
+    assert_eq!((wires["b"], wires["z00"]), (1, 0));
+  }
+
+  #[test]
+  fn cycles_and_undriven_wires_are_errors() {
+    let (wires, operations) = parse_input("x00: 1\nx00 AND a -> b\nb OR x00 -> a").unwrap();
+    let err = simulate_circuit(wires.clone(), &operations).unwrap_err();
+    assert!(err.contains("stuck on a cycle"), "{err}");
+
+    let (_, stuck) = propagate(wires, &operations).unwrap();
+    assert_eq!(stuck, ["b", "a"]);
+
+    let (wires, operations) = parse_input("x00: 1\nx00 AND q -> z00").unwrap();
+    assert!(simulate_circuit(wires, &operations).is_err());
+  }
+}
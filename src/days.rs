@@ -0,0 +1,145 @@
+//! Every day's solver behind one entry point. The examples are compiled in as they
+//! are, so each `examples/dayNN.rs` stays the only copy of its solution; their own
+//! `main`s are `pub` only so that they don't count as dead code here.
+
+use anyhow::{Result, bail};
+
+#[path = "../examples/day01.rs"]
+pub mod day01;
+#[path = "../examples/day02.rs"]
+pub mod day02;
+#[path = "../examples/day03.rs"]
+pub mod day03;
+#[path = "../examples/day04.rs"]
+pub mod day04;
+#[path = "../examples/day05.rs"]
+pub mod day05;
+#[path = "../examples/day06.rs"]
+pub mod day06;
+#[path = "../examples/day07.rs"]
+pub mod day07;
+#[path = "../examples/day08.rs"]
+pub mod day08;
+#[path = "../examples/day09.rs"]
+pub mod day09;
+#[path = "../examples/day10.rs"]
+pub mod day10;
+#[path = "../examples/day11.rs"]
+pub mod day11;
+#[path = "../examples/day12.rs"]
+pub mod day12;
+#[path = "../examples/day13.rs"]
+pub mod day13;
+#[path = "../examples/day14.rs"]
+pub mod day14;
+#[path = "../examples/day15.rs"]
+pub mod day15;
+#[path = "../examples/day16.rs"]
+pub mod day16;
+#[path = "../examples/day17.rs"]
+pub mod day17;
+#[path = "../examples/day18.rs"]
+pub mod day18;
+#[path = "../examples/day19.rs"]
+pub mod day19;
+#[path = "../examples/day20.rs"]
+pub mod day20;
+#[path = "../examples/day21.rs"]
+pub mod day21;
+#[path = "../examples/day22.rs"]
+pub mod day22;
+#[path = "../examples/day23.rs"]
+pub mod day23;
+#[path = "../examples/day24.rs"]
+pub mod day24;
+#[path = "../examples/day25.rs"]
+pub mod day25;
+
+/// Solve `part` of `day` for `input`, formatted the way the example prints it. Days
+/// whose example takes extra settings use the full puzzle's: a 101x103 room for day 14,
+/// a 71x71 memory space with 1024 fallen bytes for day 18, and cheats saving at least
+/// 100 picoseconds for day 20.
+pub fn solve(day: u8, part: u8, input: &str) -> Result<String> {
+  if !(1..=2).contains(&part) {
+    bail!("part must be 1 or 2, not {part}");
+  }
+
+  Ok(match day {
+    1 => day01::solve(input, part).to_string(),
+    2 => day02::solve(input, part).to_string(),
+    3 => day03::solve(input, part).to_string(),
+    4 => day04::solve(input, part).to_string(),
+    5 => day05::solve(input, part).to_string(),
+    6 => day06::solve(input, part)?.to_string(),
+    7 => day07::solve(input, part).to_string(),
+    8 => day08::solve(input, part).to_string(),
+    9 => day09::solve(input, part)?.to_string(),
+    10 => day10::solve(input, part)?.to_string(),
+    11 => day11::solve_stone_problem(input, if part == 1 { 25 } else { 75 }).to_string(),
+    12 => day12::solve(input, part)?.to_string(),
+    13 => day13::solve(input, part)?.to_string(),
+    14 => day14::solve(input, 101, 103, day14::SAFETY_SECONDS, part)?.to_string(),
+    15 => day15::solve(input, part).to_string(),
+    16 => day16::solve(input, part).to_string(),
+    17 => day17::solve(input, part),
+    18 => day18::solve(input, 71, 71, 1024, part),
+    19 => day19::solve(input, part).to_string(),
+    20 => day20::solve(input, day20::MIN_SAVINGS, part).to_string(),
+    21 => day21::solve(input, part)?.to_string(),
+    22 => day22::solve(input, part).to_string(),
+    23 => day23::solve(input, part)?,
+    24 => day24::solve(input, part)?,
+    25 if part == 1 => day25::solve(input)?.to_string(),
+    25 => bail!("day 25 has no part 2"),
+    _ => bail!("day must be between 1 and 25, not {day}"),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn dispatches_to_each_day() {
+    let cases = [
+      (1, 1, include_str!("../input/day01_simple.txt"), "11"),
+      (1, 2, include_str!("../input/day01_simple.txt"), "31"),
+      (5, 2, include_str!("../input/day05_simple.txt"), "123"),
+      (9, 1, include_str!("../input/day09_simple.txt"), "1928"),
+      (11, 1, include_str!("../input/day11_simple.txt"), "55312"),
+      (12, 2, include_str!("../input/day12_simple.txt"), "1206"),
+      (16, 1, include_str!("../input/day16_simple.txt"), "7036"),
+      (
+        17,
+        1,
+        include_str!("../input/day17_simple.txt"),
+        "4,6,3,5,6,3,5,2,1,0",
+      ),
+      (21, 1, include_str!("../input/day21_simple.txt"), "126384"),
+      (
+        23,
+        2,
+        include_str!("../input/day23_simple.txt"),
+        "co,de,ka,ta",
+      ),
+      (24, 1, include_str!("../input/day24_simple.txt"), "2024"),
+      (25, 1, include_str!("../input/day25_simple.txt"), "3"),
+    ];
+
+    for (day, part, input, expected) in cases {
+      assert_eq!(
+        solve(day, part, input).unwrap(),
+        expected,
+        "day {day} part {part}"
+      );
+    }
+  }
+
+  #[test]
+  fn rejects_unknown_days_and_parts() {
+    assert!(solve(25, 2, "").is_err());
+    assert!(solve(26, 1, "").is_err());
+    assert!(solve(0, 1, "").is_err());
+    assert!(solve(1, 3, "").is_err());
+  }
+}
@@ -0,0 +1,310 @@
+//! Grid primitives shared by the day examples: a signed `Position`, the four
+//! orthogonal `Direction`s, and a rectangular `Grid<T>` that bounds-checks lookups.
+
+use std::ops::{Index, IndexMut};
+
+/// A cell as (row, col). Signed, so stepping off the top or left edge gives a
+/// position the grid simply doesn't contain rather than an underflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Position {
+  pub row: i32,
+  pub col: i32,
+}
+
+impl Position {
+  pub const fn new(row: i32, col: i32) -> Self {
+    Self { row, col }
+  }
+
+  /// The adjacent position in `direction`
+  pub fn step(self, direction: Direction) -> Self {
+    let (delta_row, delta_col) = direction.delta();
+    Self::new(self.row + delta_row, self.col + delta_col)
+  }
+
+  /// The four orthogonal neighbors, in `Direction::ALL` order
+  pub fn neighbors(self) -> impl Iterator<Item = Position> {
+    Direction::ALL
+      .into_iter()
+      .map(move |direction| self.step(direction))
+  }
+
+  pub fn manhattan_distance(self, other: Position) -> u32 {
+    self.row.abs_diff(other.row) + self.col.abs_diff(other.col)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Direction {
+  Up,
+  Right,
+  Down,
+  Left,
+}
+
+impl Direction {
+  /// Clockwise from `Up`
+  pub const ALL: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+  ];
+
+  /// (row, col) change of one step; rows grow downwards
+  pub fn delta(self) -> (i32, i32) {
+    match self {
+      Direction::Up => (-1, 0),
+      Direction::Right => (0, 1),
+      Direction::Down => (1, 0),
+      Direction::Left => (0, -1),
+    }
+  }
+
+  pub fn turn_clockwise(self) -> Self {
+    match self {
+      Direction::Up => Direction::Right,
+      Direction::Right => Direction::Down,
+      Direction::Down => Direction::Left,
+      Direction::Left => Direction::Up,
+    }
+  }
+
+  pub fn turn_counterclockwise(self) -> Self {
+    self.opposite().turn_clockwise()
+  }
+
+  pub fn opposite(self) -> Self {
+    match self {
+      Direction::Up => Direction::Down,
+      Direction::Right => Direction::Left,
+      Direction::Down => Direction::Up,
+      Direction::Left => Direction::Right,
+    }
+  }
+
+  /// The direction an arrow character points: `^`, `>`, `v` or `<`
+  pub fn from_arrow(c: char) -> Option<Self> {
+    match c {
+      '^' => Some(Direction::Up),
+      '>' => Some(Direction::Right),
+      'v' => Some(Direction::Down),
+      '<' => Some(Direction::Left),
+      _ => None,
+    }
+  }
+}
+
+/// A rectangular grid stored row by row. Lookups by `Position` return `None` off the
+/// grid, while indexing with `grid[pos]` panics there like a slice would.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+  cells: Vec<T>,
+  rows: usize,
+  cols: usize,
+}
+
+impl<T> Grid<T> {
+  /// A `rows` x `cols` grid with every cell set to `fill`
+  pub fn new(rows: usize, cols: usize, fill: T) -> Self
+  where
+    T: Clone,
+  {
+    Self {
+      cells: vec![fill; rows * cols],
+      rows,
+      cols,
+    }
+  }
+
+  /// Build a grid from its rows, which must all have the same length
+  pub fn from_rows(rows: Vec<Vec<T>>) -> Self {
+    let cols = rows.first().map_or(0, Vec::len);
+    assert!(
+      rows.iter().all(|row| row.len() == cols),
+      "grid rows must all be {cols} long"
+    );
+
+    Self {
+      rows: rows.len(),
+      cols,
+      cells: rows.into_iter().flatten().collect(),
+    }
+  }
+
+  pub fn rows(&self) -> usize {
+    self.rows
+  }
+
+  pub fn cols(&self) -> usize {
+    self.cols
+  }
+
+  pub fn contains(&self, pos: Position) -> bool {
+    pos.row >= 0 && pos.col >= 0 && (pos.row as usize) < self.rows && (pos.col as usize) < self.cols
+  }
+
+  pub fn get(&self, pos: Position) -> Option<&T> {
+    self.offset(pos).map(|offset| &self.cells[offset])
+  }
+
+  pub fn get_mut(&mut self, pos: Position) -> Option<&mut T> {
+    self.offset(pos).map(|offset| &mut self.cells[offset])
+  }
+
+  /// Every position on the grid, row by row
+  pub fn positions(&self) -> impl Iterator<Item = Position> + use<T> {
+    let cols = self.cols;
+    (0..self.rows * cols)
+      .map(move |offset| Position::new((offset / cols) as i32, (offset % cols) as i32))
+  }
+
+  /// Every position with its cell, row by row
+  pub fn iter(&self) -> impl Iterator<Item = (Position, &T)> {
+    self.positions().zip(&self.cells)
+  }
+
+  /// The first position, row by row, whose cell matches
+  pub fn find(&self, mut predicate: impl FnMut(&T) -> bool) -> Option<Position> {
+    self
+      .iter()
+      .find(|(_, cell)| predicate(cell))
+      .map(|(pos, _)| pos)
+  }
+
+  /// The orthogonal neighbors of `pos` that lie on the grid
+  pub fn neighbors(&self, pos: Position) -> impl Iterator<Item = Position> + '_ {
+    pos.neighbors().filter(|&neighbor| self.contains(neighbor))
+  }
+
+  fn offset(&self, pos: Position) -> Option<usize> {
+    self
+      .contains(pos)
+      .then(|| pos.row as usize * self.cols + pos.col as usize)
+  }
+}
+
+impl Grid<char> {
+  /// One cell per character, one row per line
+  pub fn parse(input: &str) -> Self {
+    Self::from_rows(input.lines().map(|line| line.chars().collect()).collect())
+  }
+}
+
+impl<T> Index<Position> for Grid<T> {
+  type Output = T;
+
+  fn index(&self, pos: Position) -> &T {
+    self
+      .get(pos)
+      .unwrap_or_else(|| panic!("{pos:?} is outside the {}x{} grid", self.rows, self.cols))
+  }
+}
+
+impl<T> IndexMut<Position> for Grid<T> {
+  fn index_mut(&mut self, pos: Position) -> &mut T {
+    let (rows, cols) = (self.rows, self.cols);
+    self
+      .get_mut(pos)
+      .unwrap_or_else(|| panic!("{pos:?} is outside the {rows}x{cols} grid"))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn step_and_distance() {
+    let pos = Position::new(2, 3);
+    assert_eq!(pos.step(Direction::Up), Position::new(1, 3));
+    assert_eq!(pos.step(Direction::Right), Position::new(2, 4));
+    assert_eq!(
+      Position::new(0, 0).step(Direction::Left),
+      Position::new(0, -1)
+    );
+    assert_eq!(pos.manhattan_distance(Position::new(-1, 5)), 5);
+    assert_eq!(
+      pos.neighbors().collect::<Vec<_>>(),
+      [(1, 3), (2, 4), (3, 3), (2, 2)].map(|(row, col)| Position::new(row, col))
+    );
+  }
+
+  #[test]
+  fn turning() {
+    assert_eq!(Direction::Left.turn_clockwise(), Direction::Up);
+    assert_eq!(Direction::Up.turn_counterclockwise(), Direction::Left);
+    for direction in Direction::ALL {
+      assert_eq!(
+        direction.turn_clockwise().turn_counterclockwise(),
+        direction
+      );
+      assert_eq!(direction.opposite().opposite(), direction);
+      assert_eq!(
+        direction.opposite(),
+        direction.turn_clockwise().turn_clockwise()
+      );
+    }
+    assert_eq!(Direction::from_arrow('v'), Some(Direction::Down));
+    assert_eq!(Direction::from_arrow('.'), None);
+  }
+
+  #[test]
+  fn bounds_checks() {
+    let mut grid = Grid::new(2, 3, 0);
+    assert!(grid.contains(Position::new(1, 2)));
+    assert!(!grid.contains(Position::new(2, 0)));
+    assert!(!grid.contains(Position::new(0, 3)));
+    assert!(!grid.contains(Position::new(0, -1)));
+    assert_eq!(grid.get(Position::new(-1, 0)), None);
+    assert_eq!(grid.get_mut(Position::new(0, 3)), None);
+
+    grid[Position::new(1, 2)] = 7;
+    assert_eq!(grid.get(Position::new(1, 2)), Some(&7));
+  }
+
+  #[test]
+  #[should_panic(expected = "outside the 2x3 grid")]
+  fn indexing_off_the_grid_panics() {
+    let grid = Grid::new(2, 3, 0);
+    let _ = grid[Position::new(2, 0)];
+  }
+
+  #[test]
+  fn neighbors_stay_on_the_grid() {
+    let grid = Grid::new(3, 3, '.');
+    assert_eq!(grid.neighbors(Position::new(0, 0)).count(), 2);
+    assert_eq!(grid.neighbors(Position::new(0, 1)).count(), 3);
+    assert_eq!(grid.neighbors(Position::new(1, 1)).count(), 4);
+  }
+
+  #[test]
+  fn parse_round_trip() {
+    let input = "#.S\n..#\n";
+    let grid = Grid::parse(input);
+    assert_eq!((grid.rows(), grid.cols()), (2, 3));
+    assert_eq!(grid[Position::new(1, 2)], '#');
+    assert_eq!(grid.find(|&cell| cell == 'S'), Some(Position::new(0, 2)));
+
+    let rendered: String = (0..grid.rows() as i32)
+      .map(|row| {
+        let line: String = (0..grid.cols() as i32)
+          .map(|col| grid[Position::new(row, col)])
+          .collect();
+        line + "\n"
+      })
+      .collect();
+    assert_eq!(rendered, input);
+    assert_eq!(grid.positions().count(), 6);
+    assert_eq!(
+      Grid::from_rows(vec![vec!['#', '.', 'S'], vec!['.', '.', '#']]),
+      grid
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "grid rows must all be 2 long")]
+  fn ragged_rows_are_rejected() {
+    Grid::from_rows(vec![vec![1, 2], vec![3]]);
+  }
+}
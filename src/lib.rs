@@ -0,0 +1,5 @@
+// Lets the examples compiled into `days` keep importing `aoc2024::grid`
+extern crate self as aoc2024;
+
+pub mod days;
+pub mod grid;
@@ -1,3 +1,37 @@
-fn main() {
-  println!("Hello, world!");
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::fs;
+use std::io::{self, Read};
+
+/// Run any day's solver on any input
+#[derive(Parser)]
+struct Args {
+  /// Puzzle day, 1 to 25
+  #[arg(long, value_parser = clap::value_parser!(u8).range(1..=25))]
+  day: u8,
+
+  /// Puzzle part, 1 or 2
+  #[arg(long, value_parser = clap::value_parser!(u8).range(1..=2))]
+  part: u8,
+
+  /// Input file, or `-` to read standard input
+  #[arg(long)]
+  input: String,
+}
+
+fn main() -> Result<()> {
+  let args = Args::parse();
+
+  let input = if args.input == "-" {
+    let mut input = String::new();
+    io::stdin()
+      .read_to_string(&mut input)
+      .context("reading standard input")?;
+    input
+  } else {
+    fs::read_to_string(&args.input).with_context(|| format!("reading {}", args.input))?
+  };
+
+  println!("{}", aoc2024::days::solve(args.day, args.part, &input)?);
+  Ok(())
 }